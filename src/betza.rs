@@ -0,0 +1,390 @@
+//src/betza.rs
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::{board::Board, piece::{Colour, PieceType}, position::Position};
+
+/// One atom of a Betza description, e.g. the `N` in `BN`: a leap taken in each of
+/// `steps`, repeated up to `range` times in a straight line (`None` for as far as
+/// the board allows).
+#[derive(Debug, Clone, PartialEq)]
+struct Leg {
+    /// `(forward, sideways)` from the mover's point of view, so `(1, 0)` is one
+    /// square straight ahead and `(1, -1)` one square forward and to the left.
+    steps: Vec<(i32, i32)>,
+    range: Option<u32>,
+    can_move: bool,
+    can_capture: bool,
+}
+
+/// How a piece moves, written in Betza notation, e.g. `WF` for a king or `BN` for an
+/// archbishop.
+///
+/// Atoms are the leapers `W` (1,0), `F` (1,1), `D` (2,0), `N` (2,1), `A` (2,2),
+/// `H` (3,0), `C` (3,1), `Z` (3,2) and `G` (3,3), plus the shorthands `K` (`WF`),
+/// `R` (`WW`), `B` (`FF`) and `Q` (`RB`). A doubled atom (`NN`) is a rider and a
+/// number after it limits the range (`R4`, with `0` for no limit). Lower-case
+/// prefixes restrict the atom that follows: `m` to moving and `c` to capturing,
+/// and `f`, `b`, `l`, `r`, `v` and `s` to forward, backward, left, right,
+/// vertical and sideways steps. A pair such as `fl` means forward-left and a
+/// doubled letter such as `ff` the most forward steps of an oblique leaper.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Betza {
+    legs: Vec<Leg>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseBetzaError(String);
+
+impl fmt::Display for ParseBetzaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid piece definition: {}", self.0)
+    }
+}
+
+/// Every orientation of an `(a, b)` leap, as `(forward, sideways)` steps.
+fn orientations((a, b): (i32, i32)) -> Vec<(i32, i32)> {
+    let mut steps: Vec<(i32, i32)> = [(a, b), (b, a)]
+        .into_iter()
+        .flat_map(|(f, s)| [(f, s), (f, -s), (-f, s), (-f, -s)])
+        .collect();
+    steps.sort();
+    steps.dedup();
+    steps
+}
+
+/// Does the directional modifier group `group` (e.g. `f`, `fl` or `ff`) allow `step`?
+fn allows(group: &str, (forward, sideways): (i32, i32)) -> bool {
+    let vertical = forward.abs() > sideways.abs() || sideways == 0;
+    let horizontal = sideways.abs() > forward.abs() || forward == 0;
+    let one = |c: char| match c {
+        'f' => forward > 0,
+        'b' => forward < 0,
+        'l' => sideways < 0,
+        'r' => sideways > 0,
+        'v' => vertical,
+        's' => horizontal,
+        _ => false,
+    };
+
+    let mut chars = group.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => one(c),
+        (Some(c), Some(d)) if c == d => one(c) && if "fb".contains(c) { vertical } else { horizontal },
+        (Some(c), Some(d)) => one(c) && one(d),
+        _ => true,
+    }
+}
+
+/// Split directional modifiers into groups: a forward/backward letter followed by
+/// a left/right one, or a doubled letter, is one group and anything else stands
+/// alone.
+fn direction_groups(modifiers: &str) -> Vec<String> {
+    let chars: Vec<char> = modifiers.chars().collect();
+    let mut groups = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let pair = chars.get(i + 1).is_some_and(|next| {
+            *next == chars[i] || ("fb".contains(chars[i]) && "lr".contains(*next))
+        });
+        let len = if pair { 2 } else { 1 };
+        groups.push(chars[i..i + len].iter().collect());
+        i += len;
+    }
+    groups
+}
+
+impl FromStr for Betza {
+    type Err = ParseBetzaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason: &str| ParseBetzaError(format!("{} in `{}`", reason, s));
+        let mut legs = Vec::new();
+        let mut modifiers = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if "mcfblrvs".contains(c) {
+                modifiers.push(c);
+                continue;
+            }
+
+            // (leap, is a rider already) for each atom the letter stands for
+            let atoms: &[((i32, i32), bool)] = match c {
+                'W' => &[((1, 0), false)],
+                'F' => &[((1, 1), false)],
+                'D' => &[((2, 0), false)],
+                'N' => &[((2, 1), false)],
+                'A' => &[((2, 2), false)],
+                'H' => &[((3, 0), false)],
+                'C' => &[((3, 1), false)],
+                'Z' => &[((3, 2), false)],
+                'G' => &[((3, 3), false)],
+                'K' => &[((1, 0), false), ((1, 1), false)],
+                'R' => &[((1, 0), true)],
+                'B' => &[((1, 1), true)],
+                'Q' => &[((1, 0), true), ((1, 1), true)],
+                _ => return Err(error(&format!("unknown atom `{}`", c))),
+            };
+
+            let mut range = None;
+            if chars.peek() == Some(&c) {
+                chars.next();
+            } else if chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                let mut digits = String::new();
+                while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()) {
+                    digits.push(digit);
+                }
+                range = Some(digits.parse::<u32>().map_err(|_| error("bad range"))?).filter(|n| *n > 0);
+            } else {
+                range = Some(1);
+            }
+
+            let directions: String = modifiers.chars().filter(|c| !"mc".contains(*c)).collect();
+            let groups = direction_groups(&directions);
+            let (can_move, can_capture) = match (modifiers.contains('m'), modifiers.contains('c')) {
+                (false, false) => (true, true),
+                flags => flags,
+            };
+
+            for (leap, rider) in atoms {
+                let steps: Vec<(i32, i32)> = orientations(*leap)
+                    .into_iter()
+                    .filter(|step| groups.is_empty() || groups.iter().any(|group| allows(group, *step)))
+                    .collect();
+                if steps.is_empty() {
+                    return Err(error(&format!("`{}` leaves `{}` no moves", modifiers, c)));
+                }
+                let range = if *rider && range == Some(1) { None } else { range };
+                legs.push(Leg { steps, range, can_move, can_capture });
+            }
+            modifiers.clear();
+        }
+
+        if !modifiers.is_empty() {
+            return Err(error("modifiers without an atom"));
+        }
+        if legs.is_empty() {
+            return Err(error("no moves"));
+        }
+        Ok(Betza { legs })
+    }
+}
+
+impl Betza {
+    /// Turn a `(forward, sideways)` step for `colour` at `pos` into a row and column
+    /// offset. On a radial board forward is the way the side's pawns run round the
+    /// rings, and sideways is across the rings.
+    fn orient(pos: Position, colour: Colour, (forward, sideways): (i32, i32)) -> (i32, i32) {
+        let up = pos.pawn_up(colour);
+        if up.get_col() == pos.get_col() {
+            let dir = up.get_row() - pos.get_row();
+            (forward * dir, sideways * dir)
+        } else {
+            let dir = if up == pos.next_right() { 1 } else { -1 };
+            (sideways * dir, forward * dir)
+        }
+    }
+
+    /// Walk every leg from `pos`, calling `visit` with each square reached and
+    /// whether it is empty, until the leg is blocked.
+    fn walk(&self, board: &Board, pos: Position, colour: Colour, mut visit: impl FnMut(&Leg, Position, bool)) {
+        for leg in &self.legs {
+            for step in &leg.steps {
+                let (drow, dcol) = Self::orient(pos, colour, *step);
+                let mut next = pos.offset(drow, dcol);
+                let mut taken = 0;
+                while leg.range.is_none_or(|range| taken < range)
+                    && next != pos
+                    && next.is_on_board()
+                    && !board.has_friendly_piece(next, colour)
+                {
+                    let empty = board.has_no_piece(next);
+                    visit(leg, next, empty);
+                    if !empty {
+                        break;
+                    }
+                    taken += 1;
+                    next = next.offset(drow, dcol);
+                }
+            }
+        }
+    }
+
+    /// Squares a piece of `colour` on `pos` can move to.
+    pub fn moves(&self, board: &Board, pos: Position, colour: Colour) -> Vec<Position> {
+        let mut result = Vec::new();
+        self.walk(board, pos, colour, |leg, to, empty| {
+            if (empty && leg.can_move) || (!empty && leg.can_capture) {
+                result.push(to);
+            }
+        });
+        result
+    }
+
+    /// Squares a piece of `colour` on `pos` attacks, whether or not they are occupied.
+    pub fn attacks(&self, board: &Board, pos: Position, colour: Colour) -> Vec<Position> {
+        let mut result = Vec::new();
+        self.walk(board, pos, colour, |leg, to, _| {
+            if leg.can_capture {
+                result.push(to);
+            }
+        });
+        result
+    }
+}
+
+/// A piece defined at run time, played as `PieceType::Custom(letter)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PieceDefinition {
+    /// Upper case; black's pieces are written in lower case in FEN.
+    pub letter: char,
+    pub name: String,
+    pub betza: Betza,
+}
+
+/// Read piece definitions, one per line: a letter, the Betza notation and an
+/// optional name, e.g. `G WFN general`. Blank lines and lines starting with `#`
+/// are skipped. Letters already used by the built-in pieces are rejected.
+pub fn read_pieces(text: &str) -> Result<Vec<PieceDefinition>, ParseBetzaError> {
+    let mut pieces: Vec<PieceDefinition> = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |reason: &str| ParseBetzaError(format!("{} on line {}", reason, number + 1));
+
+        let mut words = line.split_whitespace();
+        let letter = match words.next().map(|word| word.chars().collect::<Vec<char>>()).as_deref() {
+            Some([letter]) if letter.is_ascii_alphabetic() => letter.to_ascii_uppercase(),
+            _ => return Err(error("expected a single letter")),
+        };
+        if PieceType::from_letter(letter).is_some() || pieces.iter().any(|piece| piece.letter == letter) {
+            return Err(error(&format!("`{}` is already taken", letter)));
+        }
+        let betza = words.next().ok_or_else(|| error("missing Betza notation"))?.parse()?;
+        let name = words.collect::<Vec<&str>>().join(" ");
+
+        pieces.push(PieceDefinition { letter, name, betza });
+    }
+
+    Ok(pieces)
+}
+
+pub fn load_pieces(path: impl AsRef<Path>) -> Result<Vec<PieceDefinition>, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    read_pieces(&text).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Topology;
+
+    fn betza(s: &str) -> Betza {
+        s.parse().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn at(notation: &str) -> Position {
+        Position::from_notation(Topology::STANDARD, notation).unwrap()
+    }
+
+    fn sorted(mut moves: Vec<Position>) -> Vec<Position> {
+        moves.sort();
+        moves
+    }
+
+    /// A lone piece's moves on an otherwise empty board match the built-in one's.
+    fn assert_moves_like(notation: &str, piece_type: PieceType, from: &str) {
+        let mut board = Board::empty(Topology::STANDARD);
+        board.place(piece_type, Colour::White, at(from));
+        let piece = board.get_piece(at(from)).unwrap();
+        assert_eq!(
+            sorted(betza(notation).moves(&board, at(from), Colour::White)),
+            sorted(piece.pseudo_legal_moves(&board)),
+            "{} from {}", notation, from,
+        );
+    }
+
+    #[test]
+    fn shorthands_match_built_in_pieces() {
+        for from in ["A1", "D4", "H5"] {
+            assert_moves_like("WF", PieceType::King, from);
+            assert_moves_like("K", PieceType::King, from);
+            assert_moves_like("N", PieceType::Knight, from);
+            assert_moves_like("R", PieceType::Rook, from);
+            assert_moves_like("Q", PieceType::Queen, from);
+            assert_moves_like("BN", PieceType::Archbishop, from);
+            assert_moves_like("RN", PieceType::Chancellor, from);
+            assert_moves_like("QN", PieceType::Amazon, from);
+        }
+        assert_eq!(betza("WF"), betza("K"));
+        assert_eq!(betza("WW"), betza("R"));
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(betza("W0"), betza("WW"));
+        assert_eq!(betza("R4").legs[0].range, Some(4));
+        assert_eq!(betza("W").legs[0].range, Some(1));
+        assert_eq!(betza("W2").legs[0].range, Some(2));
+    }
+
+    #[test]
+    fn modifier_groups() {
+        let steps = |s: &str| betza(s).legs[0].steps.clone();
+        assert_eq!(steps("fN").len(), 4);
+        assert_eq!(steps("ffN"), vec![(2, -1), (2, 1)]);
+        assert_eq!(steps("flN"), vec![(1, -2), (2, -1)]);
+        assert_eq!(steps("sW"), vec![(0, -1), (0, 1)]);
+        assert_eq!(steps("vW"), vec![(-1, 0), (1, 0)]);
+
+        let leg = &betza("mW").legs[0];
+        assert!(leg.can_move && !leg.can_capture);
+        let leg = &betza("cW").legs[0];
+        assert!(!leg.can_move && leg.can_capture);
+    }
+
+    #[test]
+    fn pawn_like() {
+        let pawn = betza("fmWfcF");
+        let mut board = Board::empty(Topology::STANDARD);
+        board.place(PieceType::Pawn, Colour::Black, at("D5"));
+        board.place(PieceType::Pawn, Colour::White, at("F5"));
+        assert_eq!(sorted(pawn.moves(&board, at("E4"), Colour::White)), sorted(vec![at("E5"), at("D5")]));
+
+        // Blocked straight ahead, and forward is down the board for black
+        board.place(PieceType::Pawn, Colour::Black, at("E5"));
+        assert_eq!(pawn.moves(&board, at("E4"), Colour::White), vec![at("D5")]);
+        assert_eq!(pawn.moves(&board, at("E6"), Colour::Black), vec![at("F5")]);
+        assert_eq!(sorted(pawn.moves(&board, at("G6"), Colour::Black)), sorted(vec![at("G5"), at("F5")]));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for bad in ["", "X", "f", "Wf", "flW", "W99999999999"] {
+            assert!(bad.parse::<Betza>().is_err(), "{} should not parse", bad);
+        }
+    }
+
+    #[test]
+    fn reads_pieces() {
+        let pieces = read_pieces("# fairy pieces\n\nG WFN general\nz fmWfcF\n").unwrap();
+        assert_eq!(pieces.len(), 2);
+        assert_eq!((pieces[0].letter, pieces[0].name.as_str()), ('G', "general"));
+        assert_eq!(pieces[0].betza, betza("WFN"));
+        assert_eq!((pieces[1].letter, pieces[1].name.as_str()), ('Z', ""));
+    }
+
+    #[test]
+    fn rejects_bad_pieces() {
+        for bad in ["N WF", "a WF", "G WF\ng N", "GG WF", "G", "1 WF", "G X"] {
+            assert!(read_pieces(bad).is_err(), "{:?} should not be read", bad);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! Build a Polyglot opening book from PGN collections.
+//!
+//! Usage: `cargo run --bin build_book -- <book.bin> <games.pgn>... [--max-ply N]`
+
+use std::fs;
+
+use chess::pgn;
+use chess::polyglot::BookBuilder;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let output = args.next().expect("Usage: build_book <book.bin> <games.pgn>... [--max-ply N]");
+
+    let mut max_ply = 24;
+    let mut inputs = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--max-ply" {
+            max_ply = args.next().and_then(|n| n.parse().ok()).expect("--max-ply needs a number");
+        } else {
+            inputs.push(arg);
+        }
+    }
+
+    let mut builder = BookBuilder::new(max_ply);
+    let mut games = 0;
+    for input in &inputs {
+        let text = fs::read_to_string(input).unwrap_or_else(|e| panic!("Could not read {}: {}", input, e));
+        for game in pgn::read_games(&text) {
+            games += 1;
+            if game.result() == "*" {
+                println!("Skipped game {}: no result", games);
+                continue;
+            }
+            let plies = builder.add_game(&game);
+            if plies < game.moves.len().min(max_ply) {
+                println!("Skipped the rest of game {} after ply {}: could not play {}", games, plies, game.moves[plies]);
+            }
+        }
+    }
+
+    let book = builder.build();
+    book.save(&output).unwrap_or_else(|e| panic!("Could not write {}: {}", output, e));
+    println!("Wrote {} entries from {} games to {}", book.len(), games, output);
+}
@@ -1,40 +1,277 @@
 //src/board.rs
 
-use crate::{piece::{Colour, Piece, PieceType}, position::Position};
+use std::fmt;
+use std::rc::Rc;
 
+use crate::{betza::PieceDefinition, piece::{Colour, Piece, PieceType}, position::{Position, Topology}, variant::{Standard, Variant}};
+
+/// Which castling moves each side has not yet given up by moving their king or rook,
+/// as the file of the rook they may still castle with. In the standard setup these
+/// are the A and H files, in Chess960 they can be any file either side of the king.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CastlingRights {
+    pub white_kingside: Option<i32>,
+    pub white_queenside: Option<i32>,
+    pub black_kingside: Option<i32>,
+    pub black_queenside: Option<i32>,
+}
+
+impl CastlingRights {
+    pub const NONE: CastlingRights = CastlingRights {
+        white_kingside: None,
+        white_queenside: None,
+        black_kingside: None,
+        black_queenside: None,
+    };
+
+    pub const ALL: CastlingRights = CastlingRights {
+        white_kingside: Some(7),
+        white_queenside: Some(0),
+        black_kingside: Some(7),
+        black_queenside: Some(0),
+    };
+
+    pub fn kingside(&self, colour: Colour) -> Option<i32> {
+        match colour {
+            Colour::White => self.white_kingside,
+            Colour::Black => self.black_kingside,
+        }
+    }
+
+    pub fn queenside(&self, colour: Colour) -> Option<i32> {
+        match colour {
+            Colour::White => self.white_queenside,
+            Colour::Black => self.black_queenside,
+        }
+    }
+
+    pub fn side(&self, colour: Colour, kingside: bool) -> Option<i32> {
+        if kingside {
+            self.kingside(colour)
+        } else {
+            self.queenside(colour)
+        }
+    }
+
+    pub fn set(&mut self, colour: Colour, kingside: bool, rook_file: Option<i32>) {
+        match (colour, kingside) {
+            (Colour::White, true) => self.white_kingside = rook_file,
+            (Colour::White, false) => self.white_queenside = rook_file,
+            (Colour::Black, true) => self.black_kingside = rook_file,
+            (Colour::Black, false) => self.black_queenside = rook_file,
+        }
+    }
+
+    fn remove(&mut self, colour: Colour, kingside: bool) {
+        self.set(colour, kingside, None);
+    }
+}
+
+/// Pieces each side holds in hand, ready to be dropped, in Crazyhouse.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pockets {
+    white: Vec<PieceType>,
+    black: Vec<PieceType>,
+}
+
+impl Pockets {
+    /// Queens first and pawns last, as in FEN.
+    const ORDER: [PieceType; 5] = [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight, PieceType::Pawn];
+
+    pub fn get(&self, colour: Colour) -> &[PieceType] {
+        match colour {
+            Colour::White => &self.white,
+            Colour::Black => &self.black,
+        }
+    }
+
+    fn get_mut(&mut self, colour: Colour) -> &mut Vec<PieceType> {
+        match colour {
+            Colour::White => &mut self.white,
+            Colour::Black => &mut self.black,
+        }
+    }
+
+    pub fn add(&mut self, colour: Colour, piece_type: PieceType) {
+        let pocket = self.get_mut(colour);
+        pocket.push(piece_type);
+        pocket.sort_by_key(|piece_type| Self::ORDER.iter().position(|p| p == piece_type).unwrap_or(Self::ORDER.len()));
+    }
+
+    /// Take one `piece_type` out of `colour`'s pocket, returning `false` if there is none.
+    pub fn take(&mut self, colour: Colour, piece_type: PieceType) -> bool {
+        let pocket = self.get_mut(colour);
+        match pocket.iter().position(|p| *p == piece_type) {
+            Some(idx) => {
+                pocket.remove(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.white.is_empty() && self.black.is_empty()
+    }
+}
+
+/// How many times each side has given check, which decides Three-check.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Checks {
+    white: u32,
+    black: u32,
+}
+
+impl Checks {
+    pub fn get(&self, colour: Colour) -> u32 {
+        match colour {
+            Colour::White => self.white,
+            Colour::Black => self.black,
+        }
+    }
+
+    pub fn set(&mut self, colour: Colour, checks: u32) {
+        match colour {
+            Colour::White => self.white = checks,
+            Colour::Black => self.black = checks,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.white == 0 && self.black == 0
+    }
+}
+
+#[derive(Clone)]
 pub struct Board {
-    pub grid: [[Option<Piece>; Board::SIZE]; Board::SIZE]
+    /// Indexed `[row][col]`, sized by `topology`.
+    pub grid: Vec<Vec<Option<Piece>>>,
+    pub topology: Topology,
+    pub turn: Colour,
+    pub castling: CastlingRights,
+    /// The square a pawn skipped over with a double step on the last move.
+    pub en_passant: Option<Position>,
+    /// Moves since the last capture or pawn move, for the fifty-move rule.
+    pub halfmove_clock: u32,
+    /// Starts at 1 and goes up after each of black's moves.
+    pub fullmove_number: u32,
+    /// Definitions of the `PieceType::Custom` pieces in play, shared between copies.
+    pub pieces: Rc<Vec<PieceDefinition>>,
+    /// The rules being played by. Boards start out playing `Standard` chess.
+    pub variant: Rc<dyn Variant>,
+    pub pockets: Pockets,
+    /// Squares holding pieces that were promoted from pawns, which Crazyhouse
+    /// turns back into pawns when they are captured.
+    pub promoted: Vec<Position>,
+    /// Checks given so far, by the side giving them.
+    pub checks: Checks,
 }
 
-impl Board {
+impl Default for Board {
+    fn default() -> Self {
+        Self::new(Board::STANDARD_SETUP)
+    }
+}
+
+/// Why a position set up by hand cannot be played from, see `Board::validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetupError(Vec<String>);
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "illegal position: {}", self.0.join(", "))
+    }
+}
 
-    pub const SIZE: usize = 8;
+/// A whole turn in Duck chess: `piece` moves to `to`, then the mover puts the duck
+/// on the empty square `duck`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DuckMove {
+    pub piece: Piece,
+    pub to: Position,
+    pub duck: Position,
+}
+
+impl Board {
 
+    /// Every piece on the board belonging to either side, which leaves out the duck.
     pub fn state(&self) -> Vec<Piece>{
         let mut pieces = Vec::new();
 
-        for row in self.grid {
-            for tile in row {
-                if let Some(piece) = tile {
-                    pieces.push(piece);
+        for row in &self.grid {
+            for piece in row.iter().flatten() {
+                if let Piece::Duck(_) = piece {
+                    continue;
                 }
+                pieces.push(*piece);
             }
         }
         pieces
     }
 
+    /// Can play start from this position under its variant? By default each side
+    /// needs exactly one king, no pawn may stand on the first or last rank and the
+    /// side that has just moved cannot be in check, see
+    /// `Variant::setup_problems`. Any en passant square must also be one a pawn
+    /// has just skipped, see `is_en_passant_square`. Every problem found is
+    /// reported.
+    pub fn validate(&self) -> Result<(), SetupError> {
+        let mut problems = self.variant.setup_problems(self);
+        if let Some(pos) = self.en_passant.filter(|pos| !self.is_en_passant_square(*pos)) {
+            problems.push(format!("no pawn can just have skipped {}", pos.to_notation()));
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(SetupError(problems)) }
+    }
+
+    /// Could a pawn of the side that has just moved have skipped `pos` with a
+    /// double step? It must be empty, one step from where that side's pawns
+    /// start, with the square it came from empty and the pawn one step beyond.
+    pub fn is_en_passant_square(&self, pos: Position) -> bool {
+        let mover = !self.turn;
+        let (from, to) = (pos.pawn_back(mover), pos.pawn_up(mover));
+        pos.is_on_board() && from.is_on_board() && to.is_on_board()
+            && from.is_starting_pawn(mover)
+            && self.has_no_piece(pos)
+            && self.has_no_piece(from)
+            && self.get_piece(to) == Some(Piece::new(PieceType::Pawn, mover, to))
+    }
+
+    /// Can `colour` no longer checkmate by any sequence of legal moves, even with
+    /// the opponent's help? This is the FIDE rule for a flag fall. True for a bare
+    /// king, for a lone bishop or knight against a bare king (against anything more
+    /// the opponent's own pieces can hem their king in, e.g. K+N v K+R), and when
+    /// the only pieces besides the kings are bishops all on squares of one colour.
+    pub fn has_insufficient_material(&self, colour: Colour) -> bool {
+        let others = |colour: Colour| -> Vec<Piece> {
+            self.state().into_iter()
+                .filter(|piece| piece.get_colour() == colour && piece.get_piece_type() != PieceType::King)
+                .collect()
+        };
+        let (ours, theirs) = (others(colour), others(!colour));
+
+        let is_minor = |piece: &Piece| matches!(piece.get_piece_type(), PieceType::Bishop | PieceType::Knight);
+        if ours.is_empty() || (ours.len() == 1 && is_minor(&ours[0]) && theirs.is_empty()) {
+            return true;
+        }
+
+        let square_colour = |piece: &Piece| (piece.get_pos().get_row() + piece.get_pos().get_col()) % 2;
+        let bishops: Vec<&Piece> = ours.iter().chain(&theirs).collect();
+        bishops.iter().all(|piece| piece.get_piece_type() == PieceType::Bishop)
+            && bishops.iter().all(|piece| square_colour(piece) == square_colour(bishops[0]))
+    }
+
+    /// See `Position::to_notation`.
     pub fn position_to_notation(pos: Position) -> String {
-        let row = pos.get_row();
-        let col = pos.get_col();
-    
-        if row < 0 || row >= Self::SIZE as i32 || col < 0 || col >= Self::SIZE as i32 {
+        if pos.is_off_board() {
             return "Invalid position".to_string();
         }
-    
-        let column_letter = (col + 65) as u8 as char; // A=65 in ASCII
-        let row_number = (Self::SIZE - row as usize).to_string(); // Row is 1-8, so we need to adjust
-    
-        format!("{}{}", column_letter, row_number)
+        pos.to_notation()
+    }
+
+    /// The inverse of `position_to_notation` on this board, e.g. `"P1"` on a radial board.
+    pub fn notation_to_position(&self, notation: &str) -> Option<Position> {
+        Position::from_notation(self.topology, notation)
     }
 
     pub fn has_piece(&self, pos: Position) -> bool {
@@ -54,89 +291,634 @@ impl Board {
         self.grid[row][col]
     }
 
+    /// The duck is nobody's enemy, as it cannot be captured.
     #[inline]
     pub fn has_enemy_piece(&self, pos: Position, ally_colour: Colour) -> bool {
-        if let Some(piece) = self.get_piece(pos) {
-            piece.get_colour() == !ally_colour
-        } else {
-            false
+        match self.get_piece(pos) {
+            Some(Piece::Duck(_)) | None => false,
+            Some(piece) => piece.get_colour() == !ally_colour,
         }
     }
 
+    /// The duck counts as friendly to both sides, so that nothing moves onto or
+    /// through it.
     pub fn has_friendly_piece(&self, pos: Position, ally_color: Colour) -> bool {
-        if let Some(piece) = self.get_piece(pos) {
-            piece.get_colour() == ally_color
+        match self.get_piece(pos) {
+            Some(Piece::Duck(_)) => true,
+            Some(piece) => piece.get_colour() == ally_color,
+            None => false,
+        }
+    }
+
+    pub fn find_king(&self, colour: Colour) -> Option<Position> {
+        self.state().into_iter()
+            .find(|piece| piece.get_piece_type() == PieceType::King && piece.get_colour() == colour)
+            .map(|piece| piece.get_pos())
+    }
+
+    /// Is `pos` attacked by any piece of colour `by`?
+    pub fn is_attacked(&self, pos: Position, by: Colour) -> bool {
+        self.state().iter()
+            .filter(|piece| piece.get_colour() == by)
+            .any(|piece| piece.attacks(self).contains(&pos))
+    }
+
+    pub fn is_in_check(&self, colour: Colour) -> bool {
+        match self.find_king(colour) {
+            Some(king) => self.is_attacked(king, !colour),
+            None => false,
+        }
+    }
+
+    /// Does moving `piece` to `to` take something, including en passant?
+    pub fn is_capture(&self, piece: Piece, to: Position) -> bool {
+        if let Piece::Captured(_, _) = piece {
+            return false;
+        }
+        let en_passant = piece.get_piece_type() == PieceType::Pawn
+            && self.en_passant == Some(to)
+            && to != piece.get_pos().pawn_up(piece.get_colour());
+        self.has_enemy_piece(to, piece.get_colour()) || en_passant
+    }
+
+    /// The piece that moving `piece` to `to` would take, if any, including a pawn
+    /// taken en passant.
+    pub fn captured_by(&self, piece: Piece, to: Position) -> Option<Piece> {
+        if !self.is_capture(piece, to) {
+            return None;
+        }
+        self.get_piece(to).or_else(|| self.get_piece(to.pawn_up(!piece.get_colour())))
+    }
+
+    /// Every legal move for the side to move, under this board's variant.
+    pub fn legal_moves(&self) -> Vec<(Piece, Position)> {
+        self.variant.legal_moves(self)
+    }
+
+    /// Drops of the pieces in the side to move's pocket onto empty squares, as
+    /// `(Piece::Captured, square)`. Pawns cannot be dropped on the first or last
+    /// rank. Whether they are legal is up to the variant.
+    pub fn drops(&self) -> Vec<(Piece, Position)> {
+        let mut piece_types = self.pockets.get(self.turn).to_vec();
+        piece_types.dedup();
+
+        let mut result = Vec::new();
+        for piece_type in piece_types {
+            for row in 0..self.topology.rows() {
+                for col in 0..self.topology.cols() {
+                    let to = Position::on(self.topology, row, col);
+                    let back_rank = to.is_back_rank(Colour::White) || to.is_back_rank(Colour::Black);
+                    if self.has_no_piece(to) && !(piece_type == PieceType::Pawn && back_rank) {
+                        result.push((Piece::Captured(piece_type, self.turn), to));
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Count the positions reached after every sequence of `depth` legal moves, with
+    /// each promotion choice counted separately, to check move generation against
+    /// known totals (e.g. 20, 400, 8902, 197281 from the standard start).
+    /// In Duck chess each place the duck can then go counts as a different move.
+    pub fn perft(&self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let mut nodes = 0;
+        for (piece, to) in self.legal_moves() {
+            let promotions = if piece.get_piece_type() == PieceType::Pawn && to.is_back_rank(!piece.get_colour()) {
+                self.variant.promotions(self)
+            } else {
+                vec![PieceType::Queen]
+            };
+            for promotion in promotions {
+                let mut board = self.clone();
+                board.apply_move(piece, to, promotion);
+                if !self.variant.has_duck() {
+                    nodes += board.perft(depth - 1);
+                    continue;
+                }
+                for duck in board.duck_squares() {
+                    let mut board = board.clone();
+                    board.place_duck(duck);
+                    nodes += board.perft(depth - 1);
+                }
+            }
+        }
+        nodes
+    }
+
+    /// Where the duck stands, or `None` before it is first placed.
+    pub fn duck(&self) -> Option<Position> {
+        self.grid.iter().flatten().flatten()
+            .find(|piece| matches!(piece, Piece::Duck(_)))
+            .map(|piece| piece.get_pos())
+    }
+
+    /// Squares the duck may be put on: any empty one, so it always has to move.
+    pub fn duck_squares(&self) -> Vec<Position> {
+        let mut result = Vec::new();
+        for row in 0..self.topology.rows() {
+            for col in 0..self.topology.cols() {
+                let pos = Position::on(self.topology, row, col);
+                if self.has_no_piece(pos) {
+                    result.push(pos);
+                }
+            }
+        }
+        result
+    }
+
+    /// Take the duck off its square, if it is on the board yet, and put it on `pos`.
+    pub fn place_duck(&mut self, pos: Position) {
+        if let Some(old) = self.duck() {
+            self.remove(old);
+        }
+        self.grid[pos.get_row() as usize][pos.get_col() as usize] = Some(Piece::Duck(pos));
+    }
+
+    /// Every legal move followed by every place the duck can then go.
+    pub fn duck_moves(&self) -> Vec<DuckMove> {
+        self.legal_moves().into_iter()
+            .flat_map(|(piece, to)| {
+                self.after_move(piece, to).duck_squares().into_iter()
+                    .map(move |duck| DuckMove { piece, to, duck })
+            })
+            .collect()
+    }
+
+    /// Play both parts of a Duck chess move, with a pawn reaching the last rank
+    /// becoming `promotion`. Like `move_piece`, an illegal move leaves the board as
+    /// it was.
+    pub fn play_duck_move(self, duck_move: DuckMove, promotion: PieceType) -> Board {
+        let DuckMove { piece, to, duck } = duck_move;
+        if !self.legal_moves().contains(&(piece, to)) {
+            println!("Move not valid!");
+            return self;
+        }
+
+        let mut board = self.clone();
+        board.apply_move(piece, to, promotion);
+        if !board.duck_squares().contains(&duck) {
+            println!("The duck cannot go there!");
+            return self;
+        }
+        board.place_duck(duck);
+        board
+    }
+
+    /// The files the king and rook end up on after castling: the G and F files
+    /// kingside and the C and D files queenside on an 8-file board, and likewise
+    /// counted in from the edges of wider boards.
+    fn castled_files(&self, kingside: bool) -> (i32, i32) {
+        let files = self.topology.cols();
+        if kingside { (files - 2, files - 3) } else { (2, 3) }
+    }
+
+    /// The square `king` is moved to in order to castle on the given side, if that
+    /// right has not been lost. When the king starts in the middle of the back rank
+    /// and the rook in the corner this is where the king ends up, as usual; in other
+    /// Chess960 setups, where the king may move one square or not at all, it is the
+    /// rook's square instead.
+    pub fn castling_target(&self, king: Piece, kingside: bool) -> Option<Position> {
+        let from = king.get_pos();
+        let rook_col = self.castling.side(king.get_colour(), kingside)?;
+        let files = self.topology.cols();
+        if from.get_col() == files / 2 && (rook_col == 0 || rook_col == files - 1) {
+            Some(Position::on(self.topology, from.get_row(), self.castled_files(kingside).0))
         } else {
-            false
+            Some(Position::on(self.topology, from.get_row(), rook_col))
+        }
+    }
+
+    /// Which side `king` castles towards by moving to `to`, if it is a castling move.
+    pub fn castling_side(&self, king: Piece, to: Position) -> Option<bool> {
+        if matches!(self.topology, Topology::Radial { .. }) || !king.get_pos().is_back_rank(king.get_colour()) {
+            return None;
         }
+        [true, false].into_iter().find(|kingside| self.castling_target(king, *kingside) == Some(to))
     }
 
-    pub fn move_piece(mut self, mut piece: Piece, to: Position, ally_colour: Colour) -> Board {
-        let row= to.get_row();
+    /// Castling moves for `king`. The king ends on the G or C file and the rook next
+    /// to it on the inside, wherever they started (see `castled_files`): every square either of them
+    /// crosses must be empty apart from the two of them, and the king must not
+    /// start in, pass through or land in check.
+    pub fn castling_moves(&self, king: Piece) -> Vec<Position> {
+        let colour = king.get_colour();
+        let from = king.get_pos();
+        let row = from.get_row();
+        let mut result = Vec::new();
+
+        if !self.variant.allows_castling()
+            || matches!(self.topology, Topology::Radial { .. })
+            || !from.is_back_rank(colour)
+            || self.variant.is_in_check(self, colour)
+        {
+            return result;
+        }
+
+        for kingside in [true, false] {
+            let Some(rook_col) = self.castling.side(colour, kingside) else {
+                continue;
+            };
+            let rook_pos = Position::on(self.topology, row, rook_col);
+            if (rook_col > from.get_col()) != kingside || self.get_piece(rook_pos) != Some(Piece::new(PieceType::Rook, colour, rook_pos)) {
+                continue;
+            }
+
+            let (king_to, rook_to) = self.castled_files(kingside);
+            let cols = [from.get_col(), king_to, rook_col, rook_to];
+            let (min, max) = (*cols.iter().min().unwrap(), *cols.iter().max().unwrap());
+            let clear = (min..=max)
+                .filter(|col| *col != from.get_col() && *col != rook_col)
+                .all(|col| self.has_no_piece(Position::on(self.topology, row, col)));
+            // Where the king lands is checked along with every other move, once the
+            // rook has moved too
+            let safe = (from.get_col().min(king_to) + 1..from.get_col().max(king_to)).all(|col| {
+                let mut board = self.clone();
+                board.remove(from);
+                board.place(PieceType::King, colour, Position::on(self.topology, row, col));
+                !self.variant.is_in_check(&board, colour)
+            });
+
+            if clear && safe {
+                result.extend(self.castling_target(king, kingside));
+            }
+        }
+
+        result
+    }
+
+    /// A copy of the board with `piece` moved to `to`, without checking the move is legal.
+    pub fn after_move(&self, piece: Piece, to: Position) -> Board {
+        let mut board = self.clone();
+        board.apply_move(piece, to, PieceType::Queen);
+        board
+    }
+
+    fn apply_move(&mut self, mut piece: Piece, to: Position, promotion: PieceType) {
+        if let Piece::Captured(piece_type, colour) = piece {
+            self.apply_drop(piece_type, colour, to);
+            return;
+        }
+
+        let moved = piece;
+        let colour = piece.get_colour();
+        let from = piece.get_pos();
+        let castling_side = match piece.get_piece_type() {
+            PieceType::King => self.castling_side(piece, to),
+            _ => None,
+        };
+
+        let mut captured = self.get_piece(to).filter(|captured| captured.get_colour() != colour);
+
+        if self.has_enemy_piece(to, colour) || piece.get_piece_type() == PieceType::Pawn {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if colour == Colour::Black {
+            self.fullmove_number += 1;
+        }
+
+        match piece.get_piece_type() {
+            PieceType::Pawn if self.en_passant == Some(to) && to != from.pawn_up(colour) => {
+                // The captured pawn is one step past the square it skipped
+                let captured_pos = to.pawn_up(!colour);
+                captured = self.grid[captured_pos.get_row() as usize][captured_pos.get_col() as usize].take();
+            }
+            _ => {}
+        }
+
+        self.grid[from.get_row() as usize][from.get_col() as usize] = None;
+
+        // Lift the rook off first, as in Chess960 the king may land where it stood
+        let mut to = to;
+        if let Some(kingside) = castling_side {
+            let row = from.get_row();
+            let (king_to, rook_to) = self.castled_files(kingside);
+            let rook_from = self.castling.side(colour, kingside).unwrap();
+            let rook = self.grid[row as usize][rook_from as usize].take();
+            self.grid[row as usize][rook_to as usize] = rook.map(|mut rook| {
+                rook.set_pos(Position::on(self.topology, row, rook_to));
+                rook
+            });
+            to = Position::on(self.topology, row, king_to);
+        }
+        let row = to.get_row();
         let col = to.get_col();
-        if piece.legal_moves(&self).contains(&to) {
-            if self.has_enemy_piece(to, ally_colour) {
-                self.get_piece( to).unwrap().set_captured();
+
+        if piece.get_piece_type() == PieceType::King {
+            self.castling.remove(colour, true);
+            self.castling.remove(colour, false);
+        }
+        for p in [from, to] {
+            for c in [Colour::White, Colour::Black] {
+                if p.is_kingside_rook(&self.castling, c) {
+                    self.castling.remove(c, true);
+                }
+                if p.is_queenside_rook(&self.castling, c) {
+                    self.castling.remove(c, false);
+                }
             }
-            self.grid[piece.get_pos().get_row() as usize][piece.get_pos().get_col() as usize] = None;
+        }
+
+        // Radial pawns step along the sectors, so compare squares rather than rows.
+        // Horde's first rank pawns double step too, but cannot be taken en passant
+        let double_step = from.pawn_up(colour).pawn_up(colour) == to;
+        self.en_passant = if piece.get_piece_type() == PieceType::Pawn && double_step && from.is_starting_pawn(colour) {
+            Some(from.pawn_up(colour))
+        } else {
+            None
+        };
+
+        piece.set_pos(to);
+        if piece.get_piece_type() == PieceType::Pawn && to.is_back_rank(!colour) {
+            piece = Piece::new(promotion, colour, to);
+        }
+        self.grid[row as usize][col as usize] = Some(piece);
+
+        let variant = Rc::clone(&self.variant);
+        variant.after_move(self, moved, to, captured);
+        self.turn = !colour;
+    }
+
+    /// Drop a `piece_type` from `colour`'s pocket onto `to`.
+    fn apply_drop(&mut self, piece_type: PieceType, colour: Colour, to: Position) {
+        self.pockets.take(colour, piece_type);
+        self.place(piece_type, colour, to);
+
+        // Like a pawn move, dropping a pawn cannot be undone
+        if piece_type == PieceType::Pawn {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if colour == Colour::Black {
+            self.fullmove_number += 1;
+        }
+        self.en_passant = None;
+
+        let variant = Rc::clone(&self.variant);
+        variant.after_move(self, Piece::Captured(piece_type, colour), to, None);
+        self.turn = !colour;
+    }
 
-            piece.set_pos(Position::new(row,col));
-            self.grid[row as usize][col as usize] = Some(piece);
+    pub fn move_piece(self, piece: Piece, to: Position, ally_colour: Colour) -> Board {
+        self.move_piece_with_promotion(piece, to, ally_colour, PieceType::Queen)
+    }
+
+    /// As `move_piece`, but a pawn reaching the last rank becomes `promotion`.
+    pub fn move_piece_with_promotion(mut self, piece: Piece, to: Position, ally_colour: Colour, promotion: PieceType) -> Board {
+        if piece.get_colour() == ally_colour && piece.legal_moves(&self).contains(&to) {
+            self.apply_move(piece, to, promotion);
         }
         else {
             println!("Move not valid!");
         }
-        return self
+        self
     }
 
-    pub fn new() -> Self {
-        let mut board = Board {
-            grid: [[None; 8]; 8]
-        };
+    /// A board of the given shape with no pieces on it and white to move.
+    pub fn empty(topology: Topology) -> Self {
+        Board {
+            grid: vec![vec![None; topology.cols() as usize]; topology.rows() as usize],
+            topology,
+            turn: Colour::White,
+            castling: CastlingRights::NONE,
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            pieces: Rc::new(Vec::new()),
+            variant: Rc::new(Standard),
+            pockets: Pockets::default(),
+            promoted: Vec::new(),
+            checks: Checks::default(),
+        }
+    }
 
-        // Rooks
-        board.grid[7][0] = Some(Piece::new(PieceType::Rook, Colour::Black, Position::new(7, 0))); // A1
-        board.grid[7][7] = Some(Piece::new(PieceType::Rook, Colour::Black, Position::new(7, 7))); // H1
+    /// The starting position of `variant`, set up to be played under it.
+    pub fn start(variant: Rc<dyn Variant>) -> Self {
+        let mut board = variant.starting_position();
+        board.variant = variant;
+        board
+    }
 
-        board.grid[0][0] = Some(Piece::new(PieceType::Rook, Colour::White, Position::new(0, 0))); // A8
-        board.grid[0][7] = Some(Piece::new(PieceType::Rook, Colour::White, Position::new(0, 7))); // H8
+    /// The definition of the custom piece written `letter`.
+    pub fn piece_definition(&self, letter: char) -> Option<&PieceDefinition> {
+        self.pieces.iter().find(|piece| piece.letter == letter.to_ascii_uppercase())
+    }
 
-        // Knights
-        board.grid[7][1] = Some(Piece::new(PieceType::Knight, Colour::Black, Position::new(7, 1))); // B1
-        board.grid[7][6] = Some(Piece::new(PieceType::Knight, Colour::Black, Position::new(7, 6))); // G1
+    /// The piece written `letter` (in either case) in FEN or SAN on this board,
+    /// including any custom pieces.
+    pub fn piece_type_from_letter(&self, letter: char) -> Option<PieceType> {
+        PieceType::from_letter(letter)
+            .or_else(|| self.piece_definition(letter).map(|piece| PieceType::Custom(piece.letter)))
+    }
 
-        board.grid[0][1] = Some(Piece::new(PieceType::Knight, Colour::White, Position::new(0, 1))); // B8
-        board.grid[0][6] = Some(Piece::new(PieceType::Knight, Colour::White, Position::new(0, 6))); // G8
+    /// Put a new piece on the board at `pos`, replacing anything already there.
+    pub fn place(&mut self, piece_type: PieceType, colour: Colour, pos: Position) {
+        self.grid[pos.get_row() as usize][pos.get_col() as usize] = Some(Piece::new(piece_type, colour, pos));
+    }
 
-        // Bishops
-        board.grid[7][2] = Some(Piece::new(PieceType::Bishop, Colour::Black, Position::new(7, 2))); // C1
-        board.grid[7][5] = Some(Piece::new(PieceType::Bishop, Colour::Black, Position::new(7, 5))); // F1
+    /// Take the piece on `pos` off the board, if there is one.
+    pub fn remove(&mut self, pos: Position) -> Option<Piece> {
+        self.grid[pos.get_row() as usize][pos.get_col() as usize].take()
+    }
 
-        board.grid[0][2] = Some(Piece::new(PieceType::Bishop, Colour::White, Position::new(0, 2))); // C8
-        board.grid[0][5] = Some(Piece::new(PieceType::Bishop, Colour::White, Position::new(0, 5))); // F8
+    /// The Chess960 index of the standard starting position.
+    pub const STANDARD_SETUP: u32 = 518;
 
-        // Royalty
-        board.grid[7][3] = Some(Piece::new(PieceType::Queen, Colour::Black, Position::new(7, 3))); // D1
-        board.grid[7][4] = Some(Piece::new(PieceType::King, Colour::Black, Position::new(7, 4)));  // E1
+    /// The Chess960 starting position with the given index, from 0 to 959, in the
+    /// standard numbering. `Board::STANDARD_SETUP` is the usual starting position.
+    pub fn new(setup: u32) -> Self {
+        Board::with_back_rank(Topology::STANDARD, &Board::chess960_back_rank(setup))
+    }
 
-        board.grid[0][3] = Some(Piece::new(PieceType::Queen, Colour::White, Position::new(0, 3))); // D8
-        board.grid[0][4] = Some(Piece::new(PieceType::King, Colour::White, Position::new(0, 4)));  // E8
+    /// A board with `back_rank` (from the A file on) and a row of pawns in front of
+    /// it for each side, facing each other, as in most rectangular variants. Castling
+    /// is allowed with the outermost rooks if the king stands between them.
+    pub fn with_back_rank(topology: Topology, back_rank: &[PieceType]) -> Self {
+        assert_eq!(back_rank.len(), topology.cols() as usize, "The back rank must fill the board");
+        let mut board = Board::empty(topology);
+        let last = topology.rows() - 1;
 
-        // Pawns
-        for i in 0..8 {
-            board.grid[6][i] = Some(Piece::new(PieceType::Pawn, Colour::Black, Position::new(6, i.try_into().unwrap()))); // 2nd row for Black
-            board.grid[1][i] = Some(Piece::new(PieceType::Pawn, Colour::White, Position::new(1, i.try_into().unwrap()))); // 7th row for White
+        for (col, piece_type) in back_rank.iter().enumerate() {
+            let at = |row: i32| Position::on(topology, row, col as i32);
+            board.place(*piece_type, Colour::White, at(0));
+            board.place(PieceType::Pawn, Colour::White, at(1));
+            board.place(PieceType::Pawn, Colour::Black, at(last - 1));
+            board.place(*piece_type, Colour::Black, at(last));
         }
 
+        let files = |piece_type: PieceType| back_rank.iter().enumerate()
+            .filter(move |(_, p)| **p == piece_type)
+            .map(|(col, _)| col as i32);
+        if let (Some(king), Some(queenside), Some(kingside)) = (files(PieceType::King).next(), files(PieceType::Rook).next(), files(PieceType::Rook).next_back()) {
+            if queenside < king && king < kingside {
+                for colour in [Colour::White, Colour::Black] {
+                    board.castling.set(colour, false, Some(queenside));
+                    board.castling.set(colour, true, Some(kingside));
+                }
+            }
+        }
 
         board
+    }
+
+    /// 5x5 Gardner minichess: the standard pieces without the second bishop, knight
+    /// and rook. Pawns never make a double step.
+    pub fn new_gardner() -> Self {
+        Board::with_back_rank(
+            Topology::GARDNER,
+            &[PieceType::Rook, PieceType::Knight, PieceType::Bishop, PieceType::Queen, PieceType::King],
+        )
+    }
 
+    /// Capablanca chess on a 10x8 board, with an archbishop between the queenside
+    /// knight and bishop and a chancellor between the kingside bishop and knight.
+    /// The king starts on the F file and castles three squares either way, to the
+    /// C or I file.
+    pub fn new_capablanca() -> Self {
+        Board::with_back_rank(
+            Topology::CAPABLANCA,
+            &[
+                PieceType::Rook, PieceType::Knight, PieceType::Archbishop, PieceType::Bishop, PieceType::Queen,
+                PieceType::King, PieceType::Bishop, PieceType::Chancellor, PieceType::Knight, PieceType::Rook,
+            ],
+        )
     }
 
+    /// 6x6 Los Alamos chess: no bishops, no castling and no pawn double steps.
+    pub fn new_los_alamos() -> Self {
+        let mut board = Board::with_back_rank(
+            Topology::LOS_ALAMOS,
+            &[PieceType::Rook, PieceType::Knight, PieceType::Queen, PieceType::King, PieceType::Knight, PieceType::Rook],
+        );
+        board.castling = CastlingRights::NONE;
+        board
+    }
+
+    /// Cylinder chess: the standard setup on a board whose A and H files are joined.
+    pub fn new_cylinder() -> Self {
+        Board::with_back_rank(Topology::CYLINDER, &Board::chess960_back_rank(Board::STANDARD_SETUP))
+    }
+
+    /// Torus chess, where the first and eighth ranks are joined as well as the A
+    /// and H files. Each army is moved one rank in from the edge so the back ranks
+    /// are not next to each other. That takes the kings off their first rank, so
+    /// there is no castling, and the pawns off their second, so no double steps.
+    pub fn new_torus() -> Self {
+        let topology = Topology::TORUS;
+        let mut board = Board::empty(topology);
+        let last = topology.rows() - 1;
+
+        for (col, piece_type) in Board::chess960_back_rank(Board::STANDARD_SETUP).into_iter().enumerate() {
+            let at = |row: i32| Position::on(topology, row, col as i32);
+            board.place(piece_type, Colour::White, at(1));
+            board.place(PieceType::Pawn, Colour::White, at(2));
+            board.place(PieceType::Pawn, Colour::Black, at(last - 2));
+            board.place(piece_type, Colour::Black, at(last - 1));
+        }
+        board
+    }
+
+    /// White's back rank in Chess960 setup `index`: the light-squared bishop, the
+    /// dark-squared bishop, the queen and the two knights are placed in turn, each
+    /// chosen by one digit of the index, and the king goes between the rooks on the
+    /// three files left.
+    pub fn chess960_back_rank(index: u32) -> [PieceType; 8] {
+        assert!(index < 960, "Chess960 setups are numbered 0 to 959");
+        const KNIGHTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+        let mut rank: [Option<PieceType>; 8] = [None; 8];
+        let mut n = index as usize;
+        rank[2 * (n % 4) + 1] = Some(PieceType::Bishop);
+        n /= 4;
+        rank[2 * (n % 4)] = Some(PieceType::Bishop);
+        n /= 4;
+
+        let empty = |rank: &[Option<PieceType>; 8]| -> Vec<usize> { (0..8).filter(|col| rank[*col].is_none()).collect() };
+        rank[empty(&rank)[n % 6]] = Some(PieceType::Queen);
+        n /= 6;
+
+        let (first, second) = KNIGHTS[n];
+        let files = empty(&rank);
+        rank[files[first]] = Some(PieceType::Knight);
+        rank[files[second]] = Some(PieceType::Knight);
+
+        let files = empty(&rank);
+        for (col, piece_type) in files.into_iter().zip([PieceType::Rook, PieceType::King, PieceType::Rook]) {
+            rank[col] = Some(piece_type);
+        }
+
+        rank.map(Option::unwrap)
+    }
+
+    /// The radial starting position on `Topology::RADIAL`. Each side has a file of
+    /// rook, knight, bishop and king and a file of rook, knight, bishop and queen
+    /// (outer ring to inner ring) side by side, with a file of pawns on either side.
+    /// The two armies face each other across the circle.
+    pub fn new_radial() -> Self {
+        let topology = Topology::RADIAL;
+        let mut board = Board::empty(topology);
+
+        let king_file = [PieceType::Rook, PieceType::Knight, PieceType::Bishop, PieceType::King];
+        let queen_file = [PieceType::Rook, PieceType::Knight, PieceType::Bishop, PieceType::Queen];
+
+        for ring in 0..topology.rows() {
+            let at = |sector: i32| Position::on(topology, ring, sector);
+            let piece_types = (king_file[ring as usize], queen_file[ring as usize]);
+
+            board.place(piece_types.0, Colour::White, at(0));
+            board.place(piece_types.1, Colour::White, at(-1));
+            board.place(PieceType::Pawn, Colour::White, at(1));
+            board.place(PieceType::Pawn, Colour::White, at(-2));
+
+            board.place(piece_types.0, Colour::Black, at(8));
+            board.place(piece_types.1, Colour::Black, at(7));
+            board.place(PieceType::Pawn, Colour::Black, at(9));
+            board.place(PieceType::Pawn, Colour::Black, at(6));
+        }
+
+        board
+    }
+
+    /// The squares `colour` can see: those their own pieces stand on and every
+    /// square those pieces could move to. Only these are shown in fog of war.
+    pub fn visible_to(&self, colour: Colour) -> Vec<Position> {
+        let mut result: Vec<Position> = self.state().into_iter()
+            .filter(|piece| piece.get_colour() == colour)
+            .flat_map(|piece| {
+                let mut squares = piece.pseudo_legal_moves(self);
+                squares.push(piece.get_pos());
+                squares
+            })
+            .collect();
+        result.sort();
+        result.dedup();
+        result
+    }
+
+    /// Can the side to move see `pos`? Always, unless the variant hides the board.
+    fn is_shown(&self, pos: Position) -> bool {
+        !self.variant.has_fog() || self.visible_to(self.turn).contains(&pos)
+    }
+
+    /// Print the board as the side to move sees it, with squares they cannot see
+    /// shown as `?` in fog of war.
     pub fn display(&self) {
-        for (row_idx, row) in self.grid.iter().enumerate() {
+        if let Topology::Radial { .. } = self.topology {
+            print!("{}", self.render_radial());
+            return;
+        }
+
+        for (row_idx, row) in self.grid.iter().enumerate().rev() {
             for (col_idx, tile) in row.iter().enumerate() {
                 match tile {
+                    _ if !self.is_shown(Position::on(self.topology, row_idx as i32, col_idx as i32)) => print!("?"),
                     Some(piece) => print!("{}", piece),
                     None => print!("{}", 
                         if (row_idx + col_idx) % 2 != 0 {"■"}
@@ -144,16 +926,357 @@ impl Board {
                     )
                 }
             }
-            println!("");
+            println!();
         }
 
         //Letter/Num Key Map:
-        for (row_idx, row) in self.grid.iter().enumerate() {
-            for (col_idx, tile) in row.iter().enumerate() {
-                print!("{}",Self::position_to_notation(Position::new(row_idx as i32,col_idx as i32)));
+        for (row_idx, row) in self.grid.iter().enumerate().rev() {
+            for (col_idx, _) in row.iter().enumerate() {
+                print!("{}",Self::position_to_notation(Position::on(self.topology, row_idx as i32,col_idx as i32)));
                 print!(" ");
             }
-            println!("");
+            println!();
+        }
+
+        if self.variant.has_pockets() {
+            for colour in [Colour::White, Colour::Black] {
+                let pocket: Vec<String> = self.pockets.get(colour).iter()
+                    .map(|piece_type| Piece::Captured(*piece_type, colour).to_string())
+                    .collect();
+                println!("{:?} in hand: {}", colour, pocket.join(" "));
+            }
+        }
+    }
+
+    /// Draw a radial board as concentric rings seen from above, with the outer ring
+    /// outermost and white's home sectors at the bottom. Sectors are lettered around
+    /// the outside and rings numbered along the left-hand spoke, matching
+    /// `position_to_notation`. In fog of war only what the side to move can see is drawn.
+    pub fn render_radial(&self) -> String {
+        const HOLE: f64 = 2.0; // radius of the empty centre, in lines
+        const RING_WIDTH: f64 = 3.0; // in lines
+        const ASPECT: f64 = 2.0; // terminal characters are about twice as tall as they are wide
+
+        let rings = self.topology.rows();
+        let sectors = self.topology.cols();
+        let step = std::f64::consts::TAU / sectors as f64;
+        let outer = HOLE + RING_WIDTH * rings as f64;
+        let ring_radius = |ring: i32| HOLE + RING_WIDTH * (rings - ring) as f64 - RING_WIDTH / 2.0;
+
+        let cy = (outer + 1.0).ceil() as i32;
+        let cx = ((outer + 2.0) * ASPECT).ceil() as i32;
+        let mut canvas = vec![vec![' '; (2 * cx + 1) as usize]; (2 * cy + 1) as usize];
+
+        // Angles run clockwise from straight down, so screen x = cx - r.sin, y = cy + r.cos
+        let mut plot = |radius: f64, angle: f64, c: char| {
+            let x = (cx as f64 - ASPECT * radius * angle.sin()).round() as usize;
+            let y = (cy as f64 + radius * angle.cos()).round() as usize;
+            canvas[y][x] = c;
+        };
+
+        for y in 0..=2 * cy {
+            for x in 0..=2 * cx {
+                let dx = (cx - x) as f64 / ASPECT;
+                let dy = (y - cy) as f64;
+                let radius = dx.hypot(dy);
+                if !(HOLE..outer).contains(&radius) {
+                    continue;
+                }
+                let ring = rings - 1 - ((radius - HOLE) / RING_WIDTH) as i32;
+                let sector = (dx.atan2(dy).rem_euclid(std::f64::consts::TAU) / step) as i32;
+                plot(radius, dx.atan2(dy), if (ring + sector) % 2 != 0 { '▒' } else { '░' });
+            }
+        }
+
+        for ring in 0..rings {
+            for sector in 0..sectors {
+                let angle = (sector as f64 + 0.5) * step;
+                let pos = Position::on(self.topology, ring, sector);
+                match self.get_piece(pos) {
+                    _ if !self.is_shown(pos) => plot(ring_radius(ring), angle, '?'),
+                    Some(piece) => plot(ring_radius(ring), angle, piece.to_string().chars().next().unwrap()),
+                    None if (ring + sector) % 2 != 0 => plot(ring_radius(ring), angle, '▒'),
+                    None => plot(ring_radius(ring), angle, '░'),
+                }
+            }
+            let number = char::from_digit((ring + 1) as u32, 36).unwrap_or('?');
+            plot(ring_radius(ring), std::f64::consts::FRAC_PI_2, number);
+        }
+
+        for sector in 0..sectors {
+            let letter = (b'A' + sector as u8) as char;
+            plot(outer + 1.0, (sector as f64 + 0.5) * step, letter);
+        }
+
+        let mut result = String::new();
+        for line in canvas {
+            result.push_str(line.iter().collect::<String>().trim_end());
+            result.push('\n');
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fen, position::{A5, D5, D6, E2, E4, E6, H5}};
+
+    fn board(fen: &str) -> Board {
+        fen::from_fen(Topology::STANDARD, fen).unwrap()
+    }
+
+    #[test]
+    fn perft_standard_start() {
+        let board = Board::new(Board::STANDARD_SETUP);
+        let counts: Vec<u64> = (1..=4).map(|depth| board.perft(depth)).collect();
+        assert_eq!(counts, [20, 400, 8902, 197281]);
+    }
+
+    #[test]
+    fn perft_radial_start() {
+        let board = Board::new_radial();
+        let counts: Vec<u64> = (1..=3).map(|depth| board.perft(depth)).collect();
+        assert_eq!(counts, [20, 400, 8584]);
+    }
+
+    #[test]
+    fn rook_goes_round_the_ring() {
+        let mut board = Board::empty(Topology::RADIAL);
+        let at = |notation: &str| Position::from_notation(Topology::RADIAL, notation).unwrap();
+        let (from, behind) = (at("A1"), at("P1"));
+        board.place(PieceType::Rook, Colour::White, from);
+
+        let rook = board.get_piece(from).unwrap();
+        let moves = rook.legal_moves(&board);
+        assert!(moves.contains(&behind));
+        let on_ring = moves.iter().filter(|to| to.get_row() == from.get_row()).count();
+        assert_eq!(on_ring, 15);
+
+        // A blocker on the ring stops the rook going round either way past it
+        board.place(PieceType::Pawn, Colour::White, at("I1"));
+        let rook = board.get_piece(from).unwrap();
+        let on_ring = rook.legal_moves(&board).into_iter().filter(|to| to.get_row() == from.get_row()).count();
+        assert_eq!(on_ring, 14);
+    }
+
+    #[test]
+    fn radial_en_passant() {
+        let mut board = Board::new_radial();
+        let at = |notation: &str| Position::from_notation(Topology::RADIAL, notation).unwrap();
+        let (from, to, skipped, black) = (at("B1"), at("D1"), at("C1"), at("D2"));
+        board.remove(black);
+        board.place(PieceType::Pawn, Colour::Black, black);
+
+        let board = board.after_move(board.get_piece(from).unwrap(), to);
+        assert_eq!(board.en_passant, Some(skipped));
+
+        let pawn = board.get_piece(black).unwrap();
+        assert!(pawn.legal_moves(&board).contains(&skipped));
+        let board = board.after_move(pawn, skipped);
+        assert!(board.has_no_piece(to));
+        assert_eq!(board.get_piece(skipped).map(|piece| piece.get_colour()), Some(Colour::Black));
+    }
+
+    #[test]
+    fn validates_en_passant_squares() {
+        assert!(board("4k3/8/8/8/4P3/8/8/4K3 b - e3").validate().is_ok());
+        assert!(board("4k3/8/8/3p4/8/8/8/4K3 w - d6").validate().is_ok());
+
+        // No pawn beyond it, the wrong rank, something on it or where the pawn came from
+        for fen in [
+            "4k3/8/8/8/3pN3/8/8/4K3 b - e3",
+            "4k3/8/8/8/8/4P3/8/4K3 b - e2",
+            "4k3/8/8/8/4P3/4N3/8/4K3 b - e3",
+            "4k3/8/8/8/4P3/8/4N3/4K3 b - e3",
+            "4k3/8/8/8/4P3/8/8/4K3 w - e3",
+        ] {
+            assert!(board(fen).validate().is_err(), "{}", fen);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn insufficient_material() {
+        assert!(board("4k3/8/8/8/8/8/8/4K3").has_insufficient_material(Colour::White));
+        assert!(board("4k3/8/8/8/8/8/8/4KN2").has_insufficient_material(Colour::White));
+        assert!(board("4k3/8/8/8/8/8/8/4KB2").has_insufficient_material(Colour::White));
+        assert!(!board("4k3/8/8/8/8/8/8/4KR2").has_insufficient_material(Colour::White));
+        assert!(!board("4k3/8/8/8/8/8/8/3NKN2").has_insufficient_material(Colour::White));
+
+        // A helpmate is possible against a side with pieces of its own
+        assert!(!board("4kr2/8/8/8/8/8/8/4KN2").has_insufficient_material(Colour::White));
+        assert!(!board("4k3/4p3/8/8/8/8/8/4KB2").has_insufficient_material(Colour::White));
+
+        // Bishops on squares of one colour can never mate, on both it can be done
+        assert!(board("4kb2/8/8/8/8/8/8/2B1K3").has_insufficient_material(Colour::White));
+        assert!(!board("4k1b1/8/8/8/8/8/8/2B1K3").has_insufficient_material(Colour::White));
+    }
+
+    #[test]
+    fn chess960_back_ranks() {
+        let letters = |index: u32| -> String {
+            Board::chess960_back_rank(index).iter().map(|piece_type| piece_type.to_string().to_uppercase()).collect()
+        };
+        assert_eq!(letters(Board::STANDARD_SETUP), "RNBQKBNR");
+        assert_eq!(letters(0), "BBQNNRKR");
+        assert_eq!(letters(959), "RKRNNQBB");
+    }
+
+    #[test]
+    fn castling_rights_round_trip() {
+        // X-FEN names an inner rook by its file, Shredder-FEN every rook
+        for (x_fen, shredder_fen) in [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1"),
+            ("1r2k1rr/8/8/8/8/8/8/RR2K2R w KBg - 0 1", "1r2k1rr/8/8/8/8/8/8/RR2K2R w HBg - 0 1"),
+        ] {
+            for fen in [x_fen, shredder_fen] {
+                let board = board(fen);
+                assert_eq!(fen::to_fen(&board), x_fen);
+                assert_eq!(fen::to_shredder_fen(&board), shredder_fen);
+            }
+        }
+    }
+
+    /// Castle `kingside` in `fen` and give the files the king and that rook end on.
+    fn castle(fen: &str, kingside: bool) -> String {
+        let board = board(fen);
+        let king = board.get_piece(board.find_king(Colour::White).unwrap()).unwrap();
+        let to = board.castling_target(king, kingside).unwrap();
+        assert!(king.legal_moves(&board).contains(&to), "cannot castle in {}", fen);
+        let board = board.after_move(king, to);
+        fen::to_fen(&board).split('/').next_back().unwrap().split(' ').next().unwrap().to_string()
+    }
+
+    #[test]
+    fn castles_in_chess960() {
+        assert_eq!(castle("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", true), "R4RK1");
+        assert_eq!(castle("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", false), "2KR3R");
+        // The king already on its castled square, then the rook already on its own
+        assert_eq!(castle("4k3/8/8/8/8/8/8/6KR w H - 0 1", true), "5RK1");
+        assert_eq!(castle("4k3/8/8/8/8/8/8/4KR2 w F - 0 1", true), "5RK1");
+        assert_eq!(castle("4k3/8/8/8/8/8/8/RK6 w A - 0 1", false), "2KR4");
+        assert_eq!(castle("4k3/8/8/8/8/8/8/1RK5 w B - 0 1", false), "2KR4");
+    }
+
+    #[test]
+    fn perft_chess960() {
+        for (fen, counts) in [
+            ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", [21, 528, 12189]),
+            ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", [21, 807, 18002]),
+        ] {
+            let board = board(fen);
+            assert_eq!((1..=3).map(|depth| board.perft(depth)).collect::<Vec<u64>>(), counts, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn minichess_setups() {
+        let gardner = Board::new_gardner();
+        assert_eq!(fen::to_fen(&gardner), "rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1");
+        assert_eq!((1..=2).map(|depth| gardner.perft(depth)).collect::<Vec<u64>>(), [7, 53]);
+
+        let los_alamos = Board::new_los_alamos();
+        assert_eq!(fen::to_fen(&los_alamos), "rnqknr/pppppp/6/6/PPPPPP/RNQKNR w - - 0 1");
+        assert_eq!((1..=2).map(|depth| los_alamos.perft(depth)).collect::<Vec<u64>>(), [10, 100]);
+
+        // Pawns only ever step one square on these boards
+        for board in [gardner, los_alamos] {
+            let pawn = board.get_piece(Position::on(board.topology, 1, 0)).unwrap();
+            assert_eq!(pawn.legal_moves(&board), [Position::on(board.topology, 2, 0)]);
+        }
+    }
+
+    #[test]
+    fn boards_of_any_size() {
+        let topology = Topology::Rectangular { ranks: 10, files: 12 };
+        let board = fen::from_fen(topology, "11k/12/12/12/12/12/12/12/12/K11 w - - 0 1").unwrap();
+        assert_eq!(board.find_king(Colour::Black), Some(Position::on(topology, 9, 11)));
+        assert_eq!(board.notation_to_position("L10"), board.find_king(Colour::Black));
+
+        let king = board.get_piece(board.find_king(Colour::White).unwrap()).unwrap();
+        assert_eq!(king.legal_moves(&board).len(), 3);
+        assert!(fen::from_fen(topology, "8/8/8/8/8/8/8/8").is_err());
+    }
+
+    #[test]
+    fn capablanca_setup() {
+        let board = Board::new_capablanca();
+        assert_eq!(fen::to_fen(&board), "rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR w KQkq - 0 1");
+        assert_eq!((1..=3).map(|depth| board.perft(depth)).collect::<Vec<u64>>(), [28, 784, 25228]);
+
+        // The king castles three squares either way
+        let castle = |kingside: bool| {
+            let board = fen::from_fen(Topology::CAPABLANCA, "r4k3r/10/10/10/10/10/10/R4K3R w KQkq - 0 1").unwrap();
+            let king = board.get_piece(board.find_king(Colour::White).unwrap()).unwrap();
+            let to = board.castling_target(king, kingside).unwrap();
+            assert!(king.legal_moves(&board).contains(&to));
+            fen::to_fen(&board.after_move(king, to)).split(['/', ' ']).nth(7).unwrap().to_string()
+        };
+        assert_eq!(castle(true), "R6RK1");
+        assert_eq!(castle(false), "2KR5R");
+    }
+
+    /// Where the piece on `square` can move in `fen` on a board of `topology`.
+    fn moves_on(topology: Topology, fen: &str, square: &str) -> Vec<String> {
+        let board = fen::from_fen(topology, fen).unwrap();
+        let piece = board.get_piece(board.notation_to_position(square).unwrap()).unwrap();
+        let mut moves: Vec<String> = piece.legal_moves(&board).iter().map(Position::to_notation).collect();
+        moves.sort();
+        moves
+    }
+
+    #[test]
+    fn pieces_wrap_round_a_cylinder() {
+        // A rook blocked to its right goes round the back of the board instead
+        let fen = "4k3/8/8/8/8/8/8/RK6 w - - 0 1";
+        assert!(moves_on(Topology::STANDARD, fen, "A1").iter().all(|to| to.starts_with('A')));
+        assert_eq!(moves_on(Topology::CYLINDER, fen, "A1").iter().filter(|to| to.ends_with('1')).count(), 6);
+        assert!(moves_on(Topology::CYLINDER, fen, "A1").contains(&"H1".to_string()));
+
+        let fen = "4k3/8/8/8/8/8/8/B3K3 w - - 0 1";
+        assert_eq!(moves_on(Topology::STANDARD, fen, "A1").len(), 7);
+        assert_eq!(moves_on(Topology::CYLINDER, fen, "A1"), ["B2", "B8", "C3", "C7", "D4", "D6", "E5", "F4", "F6", "G3", "G7", "H2", "H8"]);
+
+        let fen = "4k3/8/8/8/8/8/8/N3K3 w - - 0 1";
+        assert_eq!(moves_on(Topology::STANDARD, fen, "A1"), ["B3", "C2"]);
+        assert_eq!(moves_on(Topology::CYLINDER, fen, "A1"), ["B3", "C2", "G2", "H3"]);
+
+        // Pawns capture across the join too, but still promote on the last rank
+        let fen = "4k3/P7/8/8/7p/P7/8/4K3 w - - 0 1";
+        assert_eq!(moves_on(Topology::CYLINDER, fen, "A3"), ["A4", "H4"]);
+        assert_eq!(moves_on(Topology::CYLINDER, fen, "A7"), ["A8"]);
+        assert_eq!(Board::new_cylinder().perft(1), 20);
+    }
+
+    #[test]
+    fn pieces_wrap_round_a_torus() {
+        let fen = "8/8/8/8/4k3/8/P7/RK6 w - - 0 1";
+        assert_eq!(moves_on(Topology::TORUS, fen, "A1"), ["A3", "A4", "A5", "A6", "A7", "A8", "C1", "D1", "E1", "F1", "G1", "H1"]);
+
+        let fen = "8/8/8/8/4k3/8/8/N3K3 w - - 0 1";
+        assert_eq!(moves_on(Topology::TORUS, fen, "A1"), ["B3", "B7", "C2", "C8", "G2", "G8", "H3", "H7"]);
+
+        // Pawns start a rank in, so they only ever step one square
+        let board = Board::new_torus();
+        assert_eq!(fen::to_fen(&board), "8/rnbqkbnr/pppppppp/8/8/PPPPPPPP/RNBQKBNR/8 w - - 0 1");
+        assert_eq!(moves_on(Topology::TORUS, &fen::to_fen(&board), "E3"), ["E4"]);
+        // Knights can jump back across the join
+        assert_eq!(moves_on(Topology::TORUS, &fen::to_fen(&board), "B2"), ["A4", "A8", "C4", "C8", "D1", "H1"]);
+        let fen = "8/8/8/3k4/8/8/p7/1R2K3 b - - 0 1";
+        assert_eq!(moves_on(Topology::TORUS, fen, "A2"), ["A1", "B1"]);
+    }
+
+    #[test]
+    fn sees_only_where_its_pieces_can_go() {
+        let visible = Board::new(Board::STANDARD_SETUP).visible_to(Colour::White);
+        assert_eq!(visible.len(), 32);
+        assert!(visible.iter().all(|pos| pos.get_row() < 4));
+
+        // The pawn on d5 can be taken, so white sees it, but not what is behind it
+        let board = board("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2");
+        let visible = board.visible_to(Colour::White);
+        assert!(visible.contains(&D5) && visible.contains(&H5) && !visible.contains(&D6) && !visible.contains(&E6));
+        let visible = board.visible_to(Colour::Black);
+        assert!(visible.contains(&E4) && visible.contains(&A5) && !visible.contains(&E2));
+    }
+}
@@ -0,0 +1,173 @@
+//src/bughouse.rs
+
+use std::rc::Rc;
+
+use crate::{
+    board::Board,
+    pgn,
+    piece::{Colour, Piece, PieceType},
+    position::Position,
+    variant::{self, Bughouse, Outcome, Variant},
+};
+
+/// Board A or board B.
+pub const BOARD_NAMES: [char; 2] = ['A', 'B'];
+
+/// A Bughouse game: two boards played at the same time by two teams of two. White
+/// on board A partners black on board B, and the other two play together. Every
+/// piece a player captures is handed to their partner, who may drop it on their
+/// own board instead of moving.
+pub struct BughouseGame {
+    pub boards: [Board; 2],
+    /// Tag pairs for `to_bpgn`, e.g. `("WhiteA", "Alice")`. The result is added
+    /// there.
+    pub tags: Vec<(String, String)>,
+    /// Every move so far in the order they were made: the board, the side that
+    /// moved, their move number on that board and the move in SAN.
+    moves: Vec<(usize, Colour, u32, String)>,
+}
+
+impl Default for BughouseGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BughouseGame {
+    /// Both boards at the standard starting position.
+    pub fn new() -> Self {
+        let variant: Rc<dyn Variant> = Rc::new(Bughouse);
+        BughouseGame {
+            boards: [Board::start(Rc::clone(&variant)), Board::start(variant)],
+            tags: Vec::new(),
+            moves: Vec::new(),
+        }
+    }
+
+    /// Make a move on board `board` (0 for A, 1 for B), for whoever's turn it is
+    /// there, handing anything captured to the capturer's partner. Returns
+    /// `false`, changing nothing, if the move is not legal.
+    pub fn play(&mut self, board: usize, piece: Piece, to: Position, promotion: PieceType) -> bool {
+        let colour = self.boards[board].turn;
+        if piece.get_colour() != colour || !piece.legal_moves(&self.boards[board]).contains(&to) {
+            return false;
+        }
+
+        let san = pgn::to_san(&self.boards[board], piece, to, promotion);
+        let captured = self.boards[board].captured_by(piece, to)
+            .map(|captured| variant::in_hand(&self.boards[board], captured));
+        self.moves.push((board, colour, self.boards[board].fullmove_number, san));
+
+        let before = std::mem::take(&mut self.boards[board]);
+        self.boards[board] = before.move_piece_with_promotion(piece, to, colour, promotion);
+        if let Some(piece_type) = captured {
+            // The partner plays the other colour on the other board
+            self.boards[1 - board].pockets.add(!colour, piece_type);
+        }
+        true
+    }
+
+    /// The board that has finished and how, once either has.
+    pub fn outcome(&self) -> Option<(usize, Outcome)> {
+        (0..2).find_map(|board| self.boards[board].variant.outcome(&self.boards[board]).map(|outcome| (board, outcome)))
+    }
+
+    /// `"1-0"` if the team of white on board A has won, `"0-1"` if the other team
+    /// has, `"1/2-1/2"` for a draw and `"*"` while the game goes on.
+    pub fn result(&self) -> &'static str {
+        match self.outcome() {
+            None => "*",
+            Some((_, Outcome::Draw { .. })) => "1/2-1/2",
+            Some((board, Outcome::Win { winner, .. })) => {
+                // White on A and black on B are a team
+                if (winner == Colour::White) == (board == 0) { "1-0" } else { "0-1" }
+            }
+        }
+    }
+
+    /// Write the game as BPGN: the tags, then the moves of both boards in the
+    /// order they were made, numbered `1A.` and `1a.` for white and black on board
+    /// A and `1B.` and `1b.` on board B.
+    pub fn to_bpgn(&self) -> String {
+        let mut text = String::new();
+        for (name, value) in self.tags.iter().filter(|(name, _)| name != "Result") {
+            text.push_str(&pgn::write_tag(name, value));
+        }
+        text.push_str(&pgn::write_tag("Result", self.result()));
+        text.push('\n');
+
+        let moves: Vec<String> = self.moves.iter()
+            .map(|(board, colour, number, san)| {
+                let name = match colour {
+                    Colour::White => BOARD_NAMES[*board],
+                    Colour::Black => BOARD_NAMES[*board].to_ascii_lowercase(),
+                };
+                format!("{}{}. {}", number, name, san)
+            })
+            .chain([self.result().to_string()])
+            .collect();
+        text.push_str(&moves.join(" "));
+        text.push('\n');
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::{E4, G1, G3};
+
+    /// Play each `(board, san)` in turn.
+    fn play(game: &mut BughouseGame, moves: &[(usize, &str)]) {
+        for (board, san) in moves {
+            let (piece, to, promotion) = pgn::parse_san(&game.boards[*board], san)
+                .unwrap_or_else(|| panic!("{} is not legal on board {}", san, BOARD_NAMES[*board]));
+            assert!(game.play(*board, piece, to, promotion.unwrap_or(PieceType::Queen)));
+        }
+    }
+
+    #[test]
+    fn captures_go_to_the_partner() {
+        let mut game = BughouseGame::new();
+        play(&mut game, &[(0, "e4"), (0, "d5"), (0, "exd5")]);
+        assert!(game.boards[0].pockets.is_empty());
+        assert_eq!(game.boards[1].pockets.get(Colour::Black), [PieceType::Pawn]);
+
+        play(&mut game, &[(1, "d4"), (1, "P@e5"), (0, "Qxd5"), (1, "dxe5")]);
+        assert_eq!(game.boards[1].pockets.get(Colour::White), [PieceType::Pawn]);
+        assert_eq!(game.boards[0].pockets.get(Colour::Black), [PieceType::Pawn]);
+        assert!(game.boards[1].pockets.get(Colour::Black).is_empty());
+    }
+
+    #[test]
+    fn illegal_moves_change_nothing() {
+        let mut game = BughouseGame::new();
+        let knight = game.boards[0].get_piece(G1).unwrap();
+        assert!(!game.play(0, knight, G3, PieceType::Queen));
+        assert!(!game.play(1, Piece::Captured(PieceType::Pawn, Colour::White), E4, PieceType::Queen));
+        assert_eq!(game.to_bpgn(), "[Result \"*\"]\n\n*\n");
+    }
+
+    #[test]
+    fn one_board_decides_the_game() {
+        // Black on board B mates, winning for their partner, white on board A
+        let mut game = BughouseGame::new();
+        play(&mut game, &[(1, "f3"), (1, "e5"), (0, "e4"), (1, "g4"), (1, "Qh4#")]);
+        let (board, outcome) = game.outcome().unwrap();
+        assert_eq!(board, 1);
+        assert_eq!(outcome.result(), "0-1");
+        assert_eq!(game.result(), "1-0");
+
+        let mut game = BughouseGame::new();
+        play(&mut game, &[(0, "f3"), (0, "e5"), (0, "g4"), (0, "Qh4#")]);
+        assert_eq!(game.result(), "0-1");
+    }
+
+    #[test]
+    fn writes_bpgn() {
+        let mut game = BughouseGame::new();
+        game.tags.push(("WhiteA".to_string(), "Alice".to_string()));
+        play(&mut game, &[(0, "e4"), (1, "d4"), (0, "d5"), (0, "exd5"), (1, "P@e5")]);
+        assert_eq!(game.to_bpgn(), "[WhiteA \"Alice\"]\n[Result \"*\"]\n\n1A. e4 1B. d4 1a. d5 2A. exd5 1b. P@e5 *\n");
+    }
+}
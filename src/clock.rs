@@ -0,0 +1,371 @@
+//src/clock.rs
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::piece::Colour;
+
+/// How a player's clock is topped up (or held back) around each move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timing {
+    SuddenDeath,
+    /// Fischer: a fixed amount is added after every move.
+    Increment(Duration),
+    /// Bronstein: the time used is given back, up to the delay.
+    Bronstein(Duration),
+    /// Simple (US) delay: the clock only starts counting down once the delay has passed.
+    Delay(Duration),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Period {
+    /// Number of moves to be played in this period, `None` for the rest of the game.
+    pub moves: Option<u32>,
+    pub time: Duration,
+    pub timing: Timing,
+}
+
+/// A sequence of periods, e.g. `40/90,30+30` is 40 moves in 90 minutes followed by
+/// 30 minutes with a 30 second increment for the rest of the game.
+///
+/// Each period is written `[moves/]minutes[(+|b|d)seconds]` where `+` is a Fischer
+/// increment, `b` a Bronstein delay and `d` a simple delay. If the last period has a
+/// move count it repeats, so `40/90+30` gives another 90 minutes every 40 moves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeControl {
+    periods: Vec<Period>,
+}
+
+impl TimeControl {
+    pub fn new(periods: Vec<Period>) -> Self {
+        assert!(!periods.is_empty(), "A time control needs at least one period");
+        TimeControl { periods }
+    }
+
+    fn period(&self, index: usize) -> &Period {
+        &self.periods[index.min(self.periods.len() - 1)]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseTimeControlError(String);
+
+impl fmt::Display for ParseTimeControlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid time control: {}", self.0)
+    }
+}
+
+/// `amount` of `unit`s as a `Duration`, or `None` if it is negative, not a
+/// number or too long to fit.
+pub(crate) fn parse_duration(amount: &str, unit: f64) -> Option<Duration> {
+    let amount: f64 = amount.parse().ok()?;
+    if !amount.is_finite() {
+        return None;
+    }
+    Duration::try_from_secs_f64(amount * unit).ok()
+}
+
+impl FromStr for TimeControl {
+    type Err = ParseTimeControlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut periods = Vec::new();
+
+        for part in s.split(',').map(str::trim) {
+            let error = || ParseTimeControlError(part.to_string());
+
+            let (moves, rest) = match part.split_once('/') {
+                Some((moves, rest)) => (Some(moves.parse::<u32>().map_err(|_| error())?), rest),
+                None => (None, part),
+            };
+
+            let (minutes, timing) = match rest.find(['+', 'b', 'd']) {
+                Some(idx) => {
+                    let extra = parse_duration(&rest[idx + 1..], 1.0).ok_or_else(error)?;
+                    let timing = match &rest[idx..idx + 1] {
+                        "+" => Timing::Increment(extra),
+                        "b" => Timing::Bronstein(extra),
+                        _ => Timing::Delay(extra),
+                    };
+                    (&rest[..idx], timing)
+                }
+                None => (rest, Timing::SuddenDeath),
+            };
+
+            let time = parse_duration(minutes, 60.0).ok_or_else(error)?;
+            if time.is_zero() || moves == Some(0) {
+                return Err(error());
+            }
+
+            periods.push(Period { moves, time, timing });
+        }
+
+        Ok(TimeControl::new(periods))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SideClock {
+    remaining: Duration,
+    period: usize,
+    moves_in_period: u32,
+    flagged: bool,
+}
+
+/// A pair of chess clocks, one running at a time.
+pub struct Clock {
+    control: TimeControl,
+    white: SideClock,
+    black: SideClock,
+    running: Option<(Colour, Instant)>,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        let side = SideClock {
+            remaining: control.period(0).time,
+            period: 0,
+            moves_in_period: 0,
+            flagged: false,
+        };
+        Clock { control, white: side, black: side, running: None }
+    }
+
+    fn side(&self, colour: Colour) -> &SideClock {
+        match colour {
+            Colour::White => &self.white,
+            Colour::Black => &self.black,
+        }
+    }
+
+    fn side_mut(&mut self, colour: Colour) -> &mut SideClock {
+        match colour {
+            Colour::White => &mut self.white,
+            Colour::Black => &mut self.black,
+        }
+    }
+
+    /// Start `colour`'s clock. Does nothing if it is already running, so this can be
+    /// called every time the prompt is shown.
+    pub fn start(&mut self, colour: Colour) {
+        match self.running {
+            Some((running, _)) if running == colour => {}
+            _ => self.running = Some((colour, Instant::now())),
+        }
+    }
+
+    /// Time that counts against `colour` right now, after any delay is taken off.
+    fn used(&self, colour: Colour) -> Duration {
+        match self.running {
+            Some((running, since)) if running == colour => {
+                let elapsed = since.elapsed();
+                match self.control.period(self.side(colour).period).timing {
+                    Timing::Delay(delay) => elapsed.saturating_sub(delay),
+                    _ => elapsed,
+                }
+            }
+            _ => Duration::ZERO,
+        }
+    }
+
+    pub fn remaining(&self, colour: Colour) -> Duration {
+        self.side(colour).remaining.saturating_sub(self.used(colour))
+    }
+
+    /// Set `colour`'s time left, e.g. to give the other side time odds.
+    pub fn set_remaining(&mut self, colour: Colour, time: Duration) {
+        self.side_mut(colour).remaining = time;
+    }
+
+    pub fn is_flagged(&self, colour: Colour) -> bool {
+        self.side(colour).flagged || (self.side(colour).remaining <= self.used(colour))
+    }
+
+    /// Stop `colour`'s clock after they have made a move, applying the increment or
+    /// delay of the current period. Returns `false` if their flag fell first.
+    pub fn press(&mut self, colour: Colour) -> bool {
+        let elapsed = match self.running.take() {
+            Some((running, since)) if running == colour => since.elapsed(),
+            other => {
+                self.running = other;
+                return !self.is_flagged(colour);
+            }
+        };
+
+        let control = self.control.clone();
+        let side = self.side_mut(colour);
+        let period = *control.period(side.period);
+
+        let used = match period.timing {
+            Timing::Delay(delay) => elapsed.saturating_sub(delay),
+            _ => elapsed,
+        };
+        if !side.spend(used) {
+            return false;
+        }
+
+        match period.timing {
+            Timing::Increment(increment) => side.remaining = side.remaining.saturating_add(increment),
+            Timing::Bronstein(delay) => side.remaining = side.remaining.saturating_add(elapsed.min(delay)),
+            Timing::SuddenDeath | Timing::Delay(_) => {}
+        }
+
+        side.moves_in_period += 1;
+        if period.moves == Some(side.moves_in_period) {
+            side.period += 1;
+            side.moves_in_period = 0;
+            side.remaining = side.remaining.saturating_add(control.period(side.period).time);
+        }
+
+        true
+    }
+
+    /// Stop whichever clock is running without it counting as a move, e.g. while
+    /// the position is being edited. The time used so far is taken off but no
+    /// increment is added; `start` sets it going again.
+    pub fn stop(&mut self) {
+        if let Some((colour, _)) = self.running {
+            let used = self.used(colour);
+            self.running = None;
+            self.side_mut(colour).spend(used);
+        }
+    }
+}
+
+impl SideClock {
+    /// Take `used` off the time left, flagging if it runs out. Returns `false` if
+    /// it did.
+    fn spend(&mut self, used: Duration) -> bool {
+        if used >= self.remaining {
+            self.remaining = Duration::ZERO;
+            self.flagged = true;
+            return false;
+        }
+        self.remaining -= used;
+        true
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, (secs / 60) % 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else if secs < 10 {
+        format!("{:02}:{:02}.{}", minutes, seconds, duration.subsec_millis() / 100)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
+
+impl fmt::Display for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let marker = |colour: Colour| match self.running {
+            Some((running, _)) if running == colour => "*",
+            _ => " ",
+        };
+        write!(
+            f,
+            "{}White {}  {}Black {}",
+            marker(Colour::White),
+            format_duration(self.remaining(Colour::White)),
+            marker(Colour::Black),
+            format_duration(self.remaining(Colour::Black)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    /// Start and at once press `colour`'s clock, so next to no time is used.
+    fn quick_move(clock: &mut Clock, colour: Colour) {
+        clock.start(colour);
+        assert!(clock.press(colour));
+    }
+
+    #[test]
+    fn parses_time_controls() {
+        let control: TimeControl = "40/90,30+30".parse().unwrap();
+        assert_eq!(control.periods, vec![
+            Period { moves: Some(40), time: 90 * MINUTE, timing: Timing::SuddenDeath },
+            Period { moves: None, time: 30 * MINUTE, timing: Timing::Increment(Duration::from_secs(30)) },
+        ]);
+
+        let timing = |s: &str| s.parse::<TimeControl>().unwrap().periods[0].timing;
+        assert_eq!(timing("5"), Timing::SuddenDeath);
+        assert_eq!(timing("5+3"), Timing::Increment(Duration::from_secs(3)));
+        assert_eq!(timing("5b3"), Timing::Bronstein(Duration::from_secs(3)));
+        assert_eq!(timing("5d3"), Timing::Delay(Duration::from_secs(3)));
+        assert_eq!("0.5".parse::<TimeControl>().unwrap().periods[0].time, MINUTE / 2);
+    }
+
+    #[test]
+    fn rejects_bad_time_controls() {
+        for bad in [
+            "", "x", "0", "-5", "0/90", "5+", "5+x", "40/", "5,",
+            "5+-3", "NaN", "inf", "1e30", "5+1e30", "5+NaN", "5binf",
+        ] {
+            assert!(bad.parse::<TimeControl>().is_err(), "{} should not parse", bad);
+        }
+    }
+
+    #[test]
+    fn increment_is_added_after_each_move() {
+        let mut clock = Clock::new("5+3".parse().unwrap());
+        quick_move(&mut clock, Colour::White);
+        let remaining = clock.remaining(Colour::White);
+        assert!(remaining > 5 * MINUTE + Duration::from_secs(2) && remaining <= 5 * MINUTE + Duration::from_secs(3));
+        assert_eq!(clock.remaining(Colour::Black), 5 * MINUTE);
+    }
+
+    #[test]
+    fn delays_give_back_short_moves() {
+        // Bronstein gives back the time used, a simple delay never takes it
+        for control in ["5b3", "5d3"] {
+            let mut clock = Clock::new(control.parse().unwrap());
+            quick_move(&mut clock, Colour::White);
+            assert_eq!(clock.remaining(Colour::White), 5 * MINUTE, "{}", control);
+        }
+    }
+
+    #[test]
+    fn next_period_starts_after_its_moves() {
+        let mut clock = Clock::new("2/1,5".parse().unwrap());
+        quick_move(&mut clock, Colour::White);
+        assert!(clock.remaining(Colour::White) <= MINUTE);
+        quick_move(&mut clock, Colour::White);
+        assert!(clock.remaining(Colour::White) > 5 * MINUTE + MINUTE - Duration::from_secs(1));
+    }
+
+    #[test]
+    fn stopped_clocks_keep_their_time() {
+        let mut clock = Clock::new("5+3".parse().unwrap());
+        clock.start(Colour::White);
+        std::thread::sleep(Duration::from_millis(20));
+        clock.stop();
+        let remaining = clock.remaining(Colour::White);
+        assert!(remaining <= 5 * MINUTE - Duration::from_millis(20));
+        assert!(!clock.to_string().contains('*'));
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(clock.remaining(Colour::White), remaining);
+        assert!(clock.press(Colour::White));
+        assert_eq!(clock.remaining(Colour::White), remaining);
+    }
+
+    #[test]
+    fn flag_falls_when_time_runs_out() {
+        let mut clock = Clock::new("5".parse().unwrap());
+        clock.set_remaining(Colour::White, Duration::ZERO);
+        clock.start(Colour::White);
+        assert!(clock.is_flagged(Colour::White));
+        assert!(!clock.press(Colour::White));
+        assert!(!clock.is_flagged(Colour::Black));
+    }
+}
@@ -0,0 +1,320 @@
+//src/fen.rs
+
+use std::fmt;
+use std::rc::Rc;
+
+use crate::{betza::PieceDefinition, board::{Board, CastlingRights}, piece::{Colour, Piece, PieceType}, position::{Position, Topology}};
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FenError(String);
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid FEN: {}", self.0)
+    }
+}
+
+/// Write the position as FEN. Radial boards get one row per ring, innermost first,
+/// and runs of empty cells can be longer than 9.
+///
+/// Castling rights are written X-FEN style, so standard positions come out as usual:
+/// `K` or `Q` when the rook is the outermost one on that side of the king, and the
+/// rook's file letter when it is not (which can only happen in Chess960).
+pub fn to_fen(board: &Board) -> String {
+    write_fen(board, false)
+}
+
+/// As `to_fen`, but with Shredder-FEN castling rights, which always give the rook's
+/// file, e.g. `HAha` for the standard start.
+pub fn to_shredder_fen(board: &Board) -> String {
+    write_fen(board, true)
+}
+
+/// The row `colour`'s pieces start on, where their castling rooks stand.
+fn back_row(topology: Topology, colour: Colour) -> i32 {
+    match colour {
+        Colour::White => 0,
+        Colour::Black => topology.rows() - 1,
+    }
+}
+
+/// The outermost rook of `colour` on the given side of the king, which is the one
+/// `K` and `Q` refer to.
+fn outermost_rook(board: &Board, colour: Colour, king_col: i32, kingside: bool) -> Option<i32> {
+    let row = back_row(board.topology, colour);
+    let is_rook = |col: &i32| {
+        let pos = Position::on(board.topology, row, *col);
+        board.get_piece(pos) == Some(Piece::new(PieceType::Rook, colour, pos))
+    };
+    if kingside {
+        (king_col + 1..board.topology.cols()).rev().find(is_rook)
+    } else {
+        (0..king_col).find(is_rook)
+    }
+}
+
+/// Upper case for white and lower case for black.
+fn letter(piece_type: PieceType, colour: Colour) -> String {
+    match colour {
+        Colour::White => piece_type.to_string().to_uppercase(),
+        Colour::Black => piece_type.to_string(),
+    }
+}
+
+fn write_fen(board: &Board, shredder: bool) -> String {
+    let mut rows = Vec::new();
+    for (row_idx, row) in board.grid.iter().enumerate().rev() {
+        let mut text = String::new();
+        let mut empty = 0;
+        for (col_idx, tile) in row.iter().enumerate() {
+            match tile {
+                Some(piece) => {
+                    if empty > 0 {
+                        text.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    match piece {
+                        Piece::Duck(_) => text.push('*'),
+                        _ => text.push_str(&letter(piece.get_piece_type(), piece.get_colour())),
+                    }
+                    if board.promoted.contains(&Position::on(board.topology, row_idx as i32, col_idx as i32)) {
+                        text.push('~');
+                    }
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            text.push_str(&empty.to_string());
+        }
+        rows.push(text);
+    }
+
+    let mut placement = rows.join("/");
+    if board.variant.has_pockets() || !board.pockets.is_empty() {
+        let pockets: String = [Colour::White, Colour::Black].into_iter()
+            .flat_map(|colour| board.pockets.get(colour).iter().map(move |piece_type| letter(*piece_type, colour)))
+            .collect();
+        placement = format!("{}[{}]", placement, pockets);
+    }
+
+    let turn = match board.turn {
+        Colour::White => "w",
+        Colour::Black => "b",
+    };
+
+    let mut rights = String::new();
+    for colour in [Colour::White, Colour::Black] {
+        let king_col = board.find_king(colour).map_or(0, |king| king.get_col());
+        for (kingside, letter) in [(true, 'K'), (false, 'Q')] {
+            let Some(rook_col) = board.castling.side(colour, kingside) else {
+                continue;
+            };
+            let letter = if !shredder && outermost_rook(board, colour, king_col, kingside) == Some(rook_col) {
+                letter
+            } else {
+                (b'A' + rook_col as u8) as char
+            };
+            match colour {
+                Colour::White => rights.push(letter),
+                Colour::Black => rights.push(letter.to_ascii_lowercase()),
+            }
+        }
+    }
+    if rights.is_empty() {
+        rights.push('-');
+    }
+
+    let en_passant = match board.en_passant {
+        Some(pos) => pos.to_notation().to_lowercase(),
+        None => "-".to_string(),
+    };
+
+    let mut fen = format!(
+        "{} {} {} {} {} {}",
+        placement,
+        turn,
+        rights,
+        en_passant,
+        board.halfmove_clock,
+        board.fullmove_number
+    );
+    if board.variant.counts_checks() || !board.checks.is_empty() {
+        fen = format!("{} +{}+{}", fen, board.checks.get(Colour::White), board.checks.get(Colour::Black));
+    }
+    fen
+}
+
+/// Read a FEN for a board of the given topology, which FEN itself does not record.
+/// Only the piece placement is required; missing fields default to white to move,
+/// no castling, no en passant square and move 1. Castling rights may be given as
+/// `KQkq`, X-FEN or Shredder-FEN, and Crazyhouse pockets and promoted pieces
+/// (`Q~`), Three-check counters and the duck (`*`) are read too.
+pub fn from_fen(topology: Topology, fen: &str) -> Result<Board, FenError> {
+    from_fen_with_pieces(topology, Rc::new(Vec::new()), fen)
+}
+
+/// As `from_fen`, with the letters of the custom `pieces` allowed too.
+pub fn from_fen_with_pieces(topology: Topology, pieces: Rc<Vec<PieceDefinition>>, fen: &str) -> Result<Board, FenError> {
+    let error = |reason: &str| FenError(format!("{} in `{}`", reason, fen));
+    let mut fields: Vec<&str> = fen.split_whitespace().collect();
+    let placement = *fields.first().ok_or_else(|| error("no piece placement"))?;
+
+    let mut board = Board::empty(topology);
+    board.pieces = pieces;
+
+    // Three-check counters, either the checks given at the end (`+2+0`) or the
+    // checks remaining after the en passant square (`1+3`)
+    if let Some(idx) = fields.iter().position(|field| field.contains('+')) {
+        let field = fields.remove(idx);
+        let (given, counts) = match field.strip_prefix('+') {
+            Some(counts) => (true, counts),
+            None => (false, field),
+        };
+        let (white, black) = counts.split_once('+')
+            .and_then(|(white, black)| Some((white.parse::<u32>().ok()?, black.parse::<u32>().ok()?)))
+            .filter(|(white, black)| *white <= 3 && *black <= 3)
+            .ok_or_else(|| error(&format!("bad check counts `{}`", field)))?;
+        for (colour, count) in [(Colour::White, white), (Colour::Black, black)] {
+            board.checks.set(colour, if given { count } else { 3 - count });
+        }
+    }
+
+    // Crazyhouse pockets follow the placement in brackets, e.g. `[Qn]`, or as an
+    // extra row after a `/`
+    let mut rows: Vec<&str> = placement.split('/').collect();
+    let pockets = match placement.strip_suffix(']').and_then(|rest| rest.split_once('[')) {
+        Some((placement, pockets)) => {
+            rows = placement.split('/').collect();
+            Some(pockets)
+        }
+        None if rows.len() == topology.rows() as usize + 1 => rows.pop(),
+        None => None,
+    };
+    for c in pockets.unwrap_or("").chars() {
+        let piece_type = board.piece_type_from_letter(c).ok_or_else(|| error(&format!("unknown piece `{}` in pocket", c)))?;
+        let colour = if c.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+        board.pockets.add(colour, piece_type);
+    }
+    if rows.len() != topology.rows() as usize {
+        return Err(error(&format!("expected {} rows", topology.rows())));
+    }
+
+    for (row_text, row) in rows.iter().zip((0..topology.rows()).rev()) {
+        let mut col = 0;
+        let mut chars = row_text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if let Some(digit) = c.to_digit(10) {
+                let mut empty = digit as i32;
+                while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    empty = empty.checked_mul(10).and_then(|empty| empty.checked_add(digit as i32))
+                        .ok_or_else(|| error(&format!("row `{}` is too long", row_text)))?;
+                    chars.next();
+                }
+                if empty > topology.cols() - col {
+                    return Err(error(&format!("row `{}` is too long", row_text)));
+                }
+                col += empty;
+            } else if c == '~' {
+                // The piece before was promoted from a pawn
+                if col == 0 {
+                    return Err(error("`~` without a piece"));
+                }
+                board.promoted.push(Position::on(topology, row, col - 1));
+            } else {
+                let piece_type = board.piece_type_from_letter(c).ok_or_else(|| error(&format!("unknown piece `{}`", c)))?;
+                let colour = if c.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+                if col >= topology.cols() {
+                    return Err(error(&format!("row `{}` is too long", row_text)));
+                }
+                board.place(piece_type, colour, Position::on(topology, row, col));
+                col += 1;
+            }
+        }
+        if col != topology.cols() {
+            return Err(error(&format!("row `{}` is not {} cells wide", row_text, topology.cols())));
+        }
+    }
+
+    board.turn = match fields.get(1).copied().unwrap_or("w") {
+        "w" => Colour::White,
+        "b" => Colour::Black,
+        other => return Err(error(&format!("unknown side to move `{}`", other))),
+    };
+
+    board.castling = CastlingRights::NONE;
+    for c in fields.get(2).copied().unwrap_or("-").chars() {
+        if c == '-' {
+            continue;
+        }
+        let colour = if c.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+        let king = board.find_king(colour).filter(|king| king.get_row() == back_row(topology, colour));
+        let (Some(king), false) = (king, matches!(topology, Topology::Radial { .. })) else {
+            return Err(error(&format!("castling right `{}` without a king on its first rank", c)));
+        };
+
+        let (kingside, rook_col) = match c.to_ascii_uppercase() {
+            'K' => (true, outermost_rook(&board, colour, king.get_col(), true)),
+            'Q' => (false, outermost_rook(&board, colour, king.get_col(), false)),
+            file if file.is_ascii_uppercase() && (file as i32 - 'A' as i32) < topology.cols() => {
+                let col = file as i32 - 'A' as i32;
+                (col > king.get_col(), Some(col))
+            }
+            _ => return Err(error(&format!("unknown castling right `{}`", c))),
+        };
+        let Some(rook_col) = rook_col else {
+            return Err(error(&format!("castling right `{}` without a rook", c)));
+        };
+        board.castling.set(colour, kingside, Some(rook_col));
+    }
+
+    board.en_passant = match fields.get(3).copied().unwrap_or("-") {
+        "-" => None,
+        square => Some(Position::from_notation(topology, square).ok_or_else(|| error("bad en passant square"))?),
+    };
+
+    board.halfmove_clock = fields.get(4).map_or(Ok(0), |n| n.parse()).map_err(|_| error("bad halfmove clock"))?;
+    board.fullmove_number = fields.get(5).map_or(Ok(1), |n| n.parse()).map_err(|_| error("bad fullmove number"))?;
+
+    Ok(board)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radial_start_round_trips() {
+        let board = Board::new_radial();
+        let fen = to_fen(&board);
+        assert_eq!(to_fen(&from_fen(Topology::RADIAL, &fen).unwrap()), fen);
+        assert_eq!(from_fen(Topology::RADIAL, &fen).unwrap().grid, board.grid);
+    }
+
+    #[test]
+    fn long_empty_runs() {
+        let mut board = Board::empty(Topology::RADIAL);
+        board.place(PieceType::King, Colour::White, Position::on(Topology::RADIAL, 0, 0));
+        board.place(PieceType::King, Colour::Black, Position::on(Topology::RADIAL, 3, 15));
+        let fen = to_fen(&board);
+        assert_eq!(fen, "15k/16/16/K15 w - - 0 1");
+        assert_eq!(from_fen(Topology::RADIAL, &fen).unwrap().grid, board.grid);
+    }
+
+    #[test]
+    fn rejects_bad_rows() {
+        assert!(from_fen(Topology::RADIAL, "16/16/16").is_err());
+        assert!(from_fen(Topology::RADIAL, "17/16/16/16").is_err());
+        assert!(from_fen(Topology::RADIAL, "15/16/16/16").is_err());
+        assert!(from_fen(Topology::RADIAL, "99999999999/16/16/16").is_err());
+        assert!(from_fen(Topology::STANDARD, "8/8/8/8/8/8/8/44444444444444444444").is_err());
+        assert!(from_fen(Topology::STANDARD, "8/8/8/8/8/8/8/4K4").is_err());
+    }
+
+    #[test]
+    fn standard_start_round_trips() {
+        assert_eq!(to_fen(&from_fen(Topology::STANDARD, STARTING_FEN).unwrap()), STARTING_FEN);
+    }
+}
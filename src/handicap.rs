@@ -0,0 +1,217 @@
+//src/handicap.rs
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::{board::Board, clock::{self, Clock}, pgn, piece::{Colour, PieceType}, position::{Position, Topology}};
+
+/// The usual odds a stronger player gives a weaker one. Piece odds are given by
+/// white, who starts without a queen's knight, queen's rook or queen. Pawn and move
+/// is given by black, who starts without the f-pawn and so also lets white move
+/// first. Time odds leave the board alone and give white less time instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Handicap {
+    PawnAndMove,
+    Knight,
+    Rook,
+    Queen,
+    /// White starts with this much time on their clock instead of the time control's.
+    Time(Duration),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseHandicapError(String);
+
+impl fmt::Display for ParseHandicapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown handicap `{}`, expected pawn-and-move, knight, rook, queen or time:<minutes>", self.0)
+    }
+}
+
+/// Why a handicap cannot be given on a board, see `Handicap::apply`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandicapError(String);
+
+impl fmt::Display for HandicapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot give odds: {}", self.0)
+    }
+}
+
+impl FromStr for Handicap {
+    type Err = ParseHandicapError;
+
+    /// `pawn-and-move`, `knight`, `rook` or `queen`, or `time:<minutes>` for time
+    /// odds, e.g. `time:1` to give white a minute.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseHandicapError(s.to_string());
+        match s {
+            "pawn-and-move" => Ok(Handicap::PawnAndMove),
+            "knight" => Ok(Handicap::Knight),
+            "rook" => Ok(Handicap::Rook),
+            "queen" => Ok(Handicap::Queen),
+            _ => {
+                let minutes = s.strip_prefix("time:").ok_or_else(error)?;
+                let time = clock::parse_duration(minutes, 60.0).filter(|time| !time.is_zero()).ok_or_else(error)?;
+                Ok(Handicap::Time(time))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Handicap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Handicap::PawnAndMove => write!(f, "pawn-and-move"),
+            Handicap::Knight => write!(f, "knight"),
+            Handicap::Rook => write!(f, "rook"),
+            Handicap::Queen => write!(f, "queen"),
+            Handicap::Time(time) => write!(f, "time:{}", time.as_secs_f64() / 60.0),
+        }
+    }
+}
+
+impl Handicap {
+    /// The side giving the odds.
+    pub fn giver(&self) -> Colour {
+        match self {
+            Handicap::PawnAndMove => Colour::Black,
+            _ => Colour::White,
+        }
+    }
+
+    /// Does this handicap change the starting position?
+    pub fn is_setup(&self) -> bool {
+        !matches!(self, Handicap::Time(_))
+    }
+
+    /// Take the giver's piece off `board`, which should be in its starting
+    /// position. The knight, rook or queen taken is the one nearest the A file, so
+    /// this works for Chess960 setups too, and giving rook odds gives up castling
+    /// with that rook. Fails, leaving `board` alone, on a radial board or if the
+    /// giver has no such piece where it should be, e.g. no F file pawn on a
+    /// Gardner board.
+    pub fn apply(&self, board: &mut Board) -> Result<(), HandicapError> {
+        if !self.is_setup() {
+            return Ok(());
+        }
+        if let Topology::Radial { .. } = board.topology {
+            return Err(HandicapError(format!("{} odds need a rectangular board", self)));
+        }
+
+        let colour = self.giver();
+        let (back_row, pawn_row) = match colour {
+            Colour::White => (0, 1),
+            Colour::Black => (board.topology.rows() - 1, board.topology.rows() - 2),
+        };
+        let has = |pos: Position, piece_type: PieceType| {
+            pos.is_on_board() && board.get_piece(pos)
+                .is_some_and(|piece| piece.get_piece_type() == piece_type && piece.get_colour() == colour)
+        };
+
+        let pos = match self {
+            Handicap::PawnAndMove => Some(Position::on(board.topology, pawn_row, 5))
+                .filter(|pos| has(*pos, PieceType::Pawn)),
+            _ => {
+                let piece_type = match self {
+                    Handicap::Knight => PieceType::Knight,
+                    Handicap::Rook => PieceType::Rook,
+                    _ => PieceType::Queen,
+                };
+                (0..board.topology.cols())
+                    .map(|col| Position::on(board.topology, back_row, col))
+                    .find(|pos| has(*pos, piece_type))
+            }
+        };
+        let Some(pos) = pos else {
+            return Err(HandicapError(format!("{:?} has no piece to give for {} odds", colour, self)));
+        };
+
+        board.remove(pos);
+        if pos.get_row() != back_row {
+            return Ok(());
+        }
+        let col = pos.get_col();
+        for kingside in [true, false] {
+            if board.castling.side(colour, kingside) == Some(col) {
+                board.castling.set(colour, kingside, None);
+            }
+        }
+        Ok(())
+    }
+
+    /// Give the giver less time for time odds. Other handicaps leave the clock alone.
+    pub fn apply_to_clock(&self, clock: &mut Clock) {
+        if let Handicap::Time(time) = self {
+            clock.set_remaining(self.giver(), *time);
+        }
+    }
+
+    /// The PGN tags recording the handicap: `Handicap` naming the odds, as
+    /// written for `--handicap`, then `SetUp` and the starting `FEN` for piece
+    /// odds, from `board` before any move is made.
+    pub fn tags(&self, board: &Board) -> Vec<(String, String)> {
+        let mut tags = vec![("Handicap".to_string(), self.to_string())];
+        if self.is_setup() {
+            tags.extend(pgn::setup_tags(board));
+        }
+        tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen;
+
+    fn handicapped(odds: &str) -> String {
+        let mut board = Board::new(Board::STANDARD_SETUP);
+        odds.parse::<Handicap>().unwrap().apply(&mut board).unwrap();
+        fen::to_fen(&board)
+    }
+
+    #[test]
+    fn removes_the_odds() {
+        assert_eq!(handicapped("pawn-and-move"), "rnbqkbnr/ppppp1pp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(handicapped("knight"), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1");
+        assert_eq!(handicapped("rook"), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w Kkq - 0 1");
+        assert_eq!(handicapped("queen"), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1");
+        assert_eq!(handicapped("time:1"), fen::STARTING_FEN);
+    }
+
+    #[test]
+    fn refuses_boards_without_the_piece() {
+        let mut board = Board::new_gardner();
+        assert!(Handicap::PawnAndMove.apply(&mut board).is_err());
+        assert!(Handicap::Rook.apply(&mut board).is_ok());
+
+        let mut board = Board::new_radial();
+        assert!(Handicap::Queen.apply(&mut board).is_err());
+    }
+
+    #[test]
+    fn parses_odds() {
+        assert_eq!("time:1.5".parse(), Ok(Handicap::Time(Duration::from_secs(90))));
+        for bad in ["", "bishop", "time:", "time:0", "time:x", "time:-1", "time:inf", "time:NaN", "time:1e30"] {
+            assert!(bad.parse::<Handicap>().is_err(), "{} should not parse", bad);
+        }
+    }
+
+    #[test]
+    fn tags_record_the_odds() {
+        let board = Board::new(Board::STANDARD_SETUP);
+        let time: Handicap = "time:1.5".parse().unwrap();
+        assert_eq!(time.tags(&board), vec![("Handicap".to_string(), "time:1.5".to_string())]);
+
+        let mut game = pgn::Game::default();
+        game.tags.extend(time.tags(&board));
+        assert!(game.to_pgn().starts_with("[Handicap \"time:1.5\"]\n"));
+
+        let mut board = Board::new(Board::STANDARD_SETUP);
+        Handicap::Knight.apply(&mut board).unwrap();
+        let tags = Handicap::Knight.tags(&board);
+        assert_eq!(tags[0], ("Handicap".to_string(), "knight".to_string()));
+        assert_eq!(tags[1..], pgn::setup_tags(&board)[..]);
+    }
+}
@@ -0,0 +1,11 @@
+pub mod betza;
+pub mod board;
+pub mod bughouse;
+pub mod clock;
+pub mod fen;
+pub mod handicap;
+pub mod pgn;
+pub mod piece;
+pub mod polyglot;
+pub mod position;
+pub mod variant;
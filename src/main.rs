@@ -1,13 +1,125 @@
 use std::io::{self, Write};
-use board::Board;
-use piece::Piece;
-use position::Position;
+use std::rc::Rc;
+use chess::betza;
+use chess::board::{Board, DuckMove};
+use chess::bughouse::{BughouseGame, BOARD_NAMES};
+use chess::clock::{Clock, TimeControl};
+use chess::fen;
+use chess::handicap::Handicap;
+use chess::pgn;
+use chess::piece::{Colour, Piece, PieceType};
+use chess::polyglot::Book;
+use chess::position::{Position, Topology};
+use chess::variant::{self, Standard, Variant};
 
-mod piece;
-mod board;
-mod position;
 fn main() {
-    let mut board = Board::new();
+    let mut board = Board::new(Board::STANDARD_SETUP);
+
+    // e.g. `cargo run -- 5+3` or `cargo run -- 40/90,30+30`, see `TimeControl`,
+    // `--book <file.bin>` to allow playing Polyglot book moves with `book`, and
+    // `--radial` to play on the circular board, `--gardner` or `--los-alamos` for
+    // minichess, `--capablanca` for 10x8 Capablanca chess, `--cylinder` or
+    // `--torus` for boards that wrap around, `--chess960 <0-959>`
+    // for a Chess960 setup and `--fen "<fen>"` to start from a position (on a
+    // board of `--size <files>x<ranks>` if not 8x8). `--pieces <file>` defines
+    // pieces that the FEN or edit mode can use, see `betza::read_pieces`.
+    // `--variant <name>` picks the rules, and the starting position unless
+    // another one is given. `--handicap <odds>` sets up an odds game, see
+    // `Handicap`. `--bughouse` plays a four-player game on two boards instead,
+    // see `play_bughouse`.
+    let mut clock: Option<Clock> = None;
+    let mut book: Option<Book> = None;
+    let mut start: Option<String> = None;
+    let mut handicap: Option<Handicap> = None;
+    let mut pieces = Rc::new(Vec::new());
+    let mut variant: Rc<dyn Variant> = Rc::new(Standard);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--radial" {
+            board = Board::new_radial();
+        } else if arg == "--book" {
+            let path = args.next().expect("--book needs a path");
+            book = Some(Book::open(&path).unwrap_or_else(|e| panic!("Could not read {}: {}", path, e)));
+        } else if arg == "--gardner" {
+            board = Board::new_gardner();
+        } else if arg == "--capablanca" {
+            board = Board::new_capablanca();
+        } else if arg == "--los-alamos" {
+            board = Board::new_los_alamos();
+        } else if arg == "--cylinder" {
+            board = Board::new_cylinder();
+        } else if arg == "--torus" {
+            board = Board::new_torus();
+        } else if arg == "--size" {
+            let size = args.next().expect("--size needs <files>x<ranks>");
+            let (files, ranks) = size.split_once('x')
+                .and_then(|(files, ranks)| Some((files.parse().ok()?, ranks.parse().ok()?)))
+                .filter(|(files, ranks)| (1..=26).contains(files) && *ranks >= 1)
+                .unwrap_or_else(|| panic!("Invalid board size {}", size));
+            board = Board::empty(Topology::Rectangular { ranks, files });
+        } else if arg == "--chess960" {
+            let setup = args.next().and_then(|setup| setup.parse().ok()).filter(|setup| *setup < 960);
+            board = Board::new(setup.expect("--chess960 needs a setup number from 0 to 959"));
+        } else if arg == "--variant" {
+            let name = args.next().expect("--variant needs a name");
+            variant = variant::from_name(&name).unwrap_or_else(|| panic!("Unknown variant {}", name));
+            board = Board::start(Rc::clone(&variant));
+        } else if arg == "--pieces" {
+            let path = args.next().expect("--pieces needs a path");
+            pieces = Rc::new(betza::load_pieces(&path).unwrap_or_else(|e| panic!("Could not read {}: {}", path, e)));
+        } else if arg == "--fen" {
+            start = Some(args.next().expect("--fen needs a position"));
+        } else if arg == "--handicap" {
+            let odds = args.next().expect("--handicap needs the odds given");
+            match odds.parse() {
+                Ok(odds) => handicap = Some(odds),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            }
+        } else if arg == "--bughouse" {
+            play_bughouse();
+            return;
+        } else {
+            match arg.parse::<TimeControl>() {
+                Ok(control) => clock = Some(Clock::new(control)),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            }
+        }
+    }
+    let from_fen = start.is_some();
+    if let Some(start) = start {
+        board = fen::from_fen_with_pieces(board.topology, Rc::clone(&pieces), &start).unwrap_or_else(|e| panic!("{}", e));
+    }
+    board.pieces = pieces;
+    board.variant = variant;
+    if from_fen {
+        if let Err(e) = board.validate() {
+            eprintln!("{}", e);
+            return;
+        }
+    }
+
+    let mut game = pgn::Game::default();
+    if let Some(handicap) = handicap {
+        if let Err(e) = handicap.apply(&mut board) {
+            eprintln!("{}", e);
+            return;
+        }
+        match clock.as_mut() {
+            Some(clock) => handicap.apply_to_clock(clock),
+            None if !handicap.is_setup() => {
+                eprintln!("Time odds need a time control too, e.g. --handicap {} 5+0", handicap);
+                return;
+            }
+            None => {}
+        }
+        game.tags.extend(handicap.tags(&board));
+    }
     // board.display();
 
     // let my_pawn = board.get_piece(Position::new(1,1)).unwrap();
@@ -21,7 +133,17 @@ fn main() {
 
     loop {
         board.display();
-        print!("> ");
+        if let Some(outcome) = board.variant.outcome(&board) {
+            println!("{}", outcome);
+            game.tags.push(("Result".to_string(), outcome.result().to_string()));
+            println!("{}", game.to_pgn());
+            break;
+        }
+        if let Some(clock) = clock.as_mut() {
+            clock.start(board.turn);
+            println!("{}", clock);
+        }
+        print!("{:?}> ", board.turn);
         io::stdout().flush().unwrap();
 
         let mut input = String::new();
@@ -33,27 +155,309 @@ fn main() {
             break;
         }
 
-        //expect something like D2 -> A3
-        let keywords: Vec<&str> = input.split(" ").collect();
-        assert!(keywords.len() == 3);
-        assert!(keywords[1] == "->");
-        let origin: Vec<char> = keywords[0].chars().collect();
-        let destination: Vec<char> = keywords[2].chars().collect();
-        assert!(origin[0] >= 'A' && origin[0] <= 'H');
-        assert!(origin[1] >= '1' && origin[1] <= '8');
-        assert!(destination[0] >= 'A' && destination[0] <= 'H');
-        assert!(destination[1] >= '1' && destination[1] <= '8');
-        
-        let origin_str: &str = &origin.iter().collect::<String>();
-        let destination_str: &str = &destination.iter().collect::<String>();
+        if input == "fen" {
+            if board.variant.has_fog() {
+                println!("The position is hidden in fog of war.");
+            } else {
+                println!("{}", fen::to_fen(&board));
+            }
+            continue;
+        }
 
-        let origin_pos: Position = position::interpret_position(origin_str).unwrap();
-        let chosen_piece: Piece = board.get_piece(origin_pos).unwrap();
+        if input == "edit" {
+            // Nobody's time runs while the position is being set up
+            if let Some(clock) = clock.as_mut() {
+                clock.stop();
+            }
+            if let Some(edited) = edit_board(&board) {
+                board = edited;
+                game = pgn::Game { tags: pgn::setup_tags(&board), moves: Vec::new() };
+            }
+            continue;
+        }
+
+        if input == "pgn" {
+            if board.variant.has_fog() {
+                println!("The moves are hidden in fog of war.");
+            } else {
+                println!("{}", game.to_pgn());
+            }
+            continue;
+        }
 
-        let destination_pos: Position = position::interpret_position(destination_str).unwrap();
-        
-        board = board.move_piece(chosen_piece, destination_pos, chosen_piece.get_colour());
+        if let Some(depth) = input.strip_prefix("perft ") {
+            match depth.parse() {
+                Ok(depth) => println!("{} positions", board.perft(depth)),
+                Err(_) => println!("Usage: perft <depth>"),
+            }
+            continue;
+        }
 
+        let (chosen_piece, destination_pos, promotion) = if input == "book" {
+            let Some(book) = book.as_ref() else {
+                println!("No opening book loaded, start with --book <file.bin>");
+                continue;
+            };
+            match book.choose(&board) {
+                Some((piece, to, promotion)) => {
+                    println!("Book move: {} -> {}", Board::position_to_notation(piece.get_pos()), Board::position_to_notation(to));
+                    (piece, to, promotion)
+                }
+                None => {
+                    println!("Out of book.");
+                    continue;
+                }
+            }
+        } else if let Some((letter, square)) = input.split_once('@') {
+            //a drop from the pocket, like N@F3, or @E4 for a pawn
+            let piece_type = match letter.chars().next() {
+                Some(c) => board.piece_type_from_letter(c),
+                None => Some(PieceType::Pawn),
+            };
+            let (Some(piece_type), Some(destination_pos)) = (piece_type, board.notation_to_position(square)) else {
+                println!("Unknown piece or square in {}", input);
+                continue;
+            };
+            (Piece::Captured(piece_type, board.turn), destination_pos, None)
+        } else {
+            //expect something like D2 -> A3
+            let keywords: Vec<&str> = input.split_whitespace().collect();
+            let [from, "->", to] = keywords.as_slice() else {
+                println!("Enter a move as <from> -> <to>, e.g. E2 -> E4");
+                continue;
+            };
+
+            let (Some(origin_pos), Some(destination_pos)) = (board.notation_to_position(from), board.notation_to_position(to)) else {
+                println!("Unknown square in {}", input);
+                continue;
+            };
+            let Some(chosen_piece) = board.get_piece(origin_pos) else {
+                println!("There is no piece on {}", from.to_uppercase());
+                continue;
+            };
+            if let Piece::Duck(_) = chosen_piece {
+                println!("The duck is moved after your piece, not instead of it.");
+                continue;
+            }
+            if chosen_piece.get_colour() != board.turn {
+                println!("It is {:?}'s turn!", board.turn);
+                continue;
+            }
+
+            (chosen_piece, destination_pos, None)
+        };
+
+        let turn = board.turn;
+        let is_legal = chosen_piece.legal_moves(&board).contains(&destination_pos);
+
+        //then where the duck goes, like E5
+        let mut duck = None;
+        if board.variant.has_duck() && is_legal {
+            print!("Duck to> ");
+            io::stdout().flush().unwrap();
+            let mut input = String::new();
+            io::stdin().read_line(&mut input).expect("Failed to read line");
+            match board.notation_to_position(input.trim()) {
+                Some(pos) if board.after_move(chosen_piece, destination_pos).duck_squares().contains(&pos) => duck = Some(pos),
+                _ => {
+                    println!("The duck cannot go there!");
+                    continue;
+                }
+            }
+        }
+
+        if let Some(clock) = clock.as_mut() {
+            if is_legal && !clock.press(turn) {
+                board.display();
+                if board.has_insufficient_material(!turn) {
+                    println!("{:?} ran out of time, but {:?} cannot checkmate. Draw.", turn, !turn);
+                } else {
+                    println!("{:?} ran out of time. {:?} wins!", turn, !turn);
+                }
+                break;
+            }
+        }
+
+        let promotion = promotion.unwrap_or_else(|| board.variant.promotions(&board)[0]);
+        if is_legal {
+            let san = pgn::to_san(&board, chosen_piece, destination_pos, promotion);
+            game.moves.push(match duck {
+                Some(duck) => format!("{},{}", san, duck.to_notation().to_lowercase()),
+                None => san,
+            });
+        }
+        if let Some(duck) = duck {
+            let duck_move = DuckMove { piece: chosen_piece, to: destination_pos, duck };
+            board = board.play_duck_move(duck_move, promotion);
+        } else {
+            board = board.move_piece_with_promotion(chosen_piece, destination_pos, turn, promotion);
+        }
+
+        println!("\n");
+    }
+}
+
+/// Set up a position by hand, starting from `board`, until it is valid and `done`
+/// is entered, or give up with `cancel`. Pieces are placed with their FEN letter,
+/// e.g. `place N F3` or `place p E7`, and the side to move, castling rights and en
+/// passant square are given as in FEN, e.g. `turn b`, `castling Kq`,
+/// `en-passant E3` or `-` for none.
+fn edit_board(board: &Board) -> Option<Board> {
+    let mut board = board.clone();
+    println!("Editing. Commands: place <piece> <square>, remove <square>, clear, turn <w|b>, castling <rights>, en-passant <square>, fen, done, cancel");
+
+    loop {
+        board.display();
+        print!("edit> ");
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Failed to read line");
+        let words: Vec<&str> = input.split_whitespace().collect();
+
+        match words.as_slice() {
+            ["done"] => match board.validate() {
+                Ok(()) => return Some(board),
+                Err(e) => println!("{}", e),
+            },
+            ["cancel"] => return None,
+            ["fen"] if board.variant.has_fog() => println!("The position is hidden in fog of war."),
+            ["fen"] => println!("{}", fen::to_fen(&board)),
+            ["clear"] => {
+                for pos in board.state().iter().map(|piece| piece.get_pos()).chain(board.duck()) {
+                    board.remove(pos);
+                }
+                board.promoted.clear();
+                board.en_passant = None;
+                drop_lost_castling(&mut board);
+            }
+            ["place", letter, square] => {
+                let mut letters = letter.chars();
+                let (Some(c), None) = (letters.next(), letters.next()) else {
+                    println!("Give the piece as one letter, e.g. N for a white knight or n for a black one");
+                    continue;
+                };
+                let (Some(piece_type), Some(pos)) = (board.piece_type_from_letter(c), board.notation_to_position(square)) else {
+                    println!("Unknown piece or square");
+                    continue;
+                };
+                let colour = if c.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+                if piece_type == PieceType::Duck {
+                    board.place_duck(pos);
+                } else {
+                    board.place(piece_type, colour, pos);
+                }
+                board.promoted.retain(|promoted| *promoted != pos);
+                drop_lost_castling(&mut board);
+            }
+            ["remove", square] => {
+                let Some(pos) = board.notation_to_position(square) else {
+                    println!("Unknown square");
+                    continue;
+                };
+                board.remove(pos);
+                board.promoted.retain(|promoted| *promoted != pos);
+                drop_lost_castling(&mut board);
+            }
+            ["turn", turn] => edit_fen_field(&mut board, 1, turn),
+            ["castling", rights] => edit_fen_field(&mut board, 2, rights),
+            ["en-passant", square] => {
+                let before = board.clone();
+                edit_fen_field(&mut board, 3, &square.to_lowercase());
+                if board.en_passant.is_some_and(|pos| !board.is_en_passant_square(pos)) {
+                    println!("No {:?} pawn can just have skipped {}", !board.turn, square.to_uppercase());
+                    board = before;
+                }
+            }
+            _ => println!("Unknown command"),
+        }
+    }
+}
+
+/// Replace field `index` of `board`'s FEN with `value` and read it back, so the
+/// side to move, castling rights and en passant square are checked just as they
+/// are in a FEN. `board` is left alone if the result is not valid FEN.
+fn edit_fen_field(board: &mut Board, index: usize, value: &str) {
+    let fen = fen::to_fen(board);
+    let mut fields: Vec<&str> = fen.split_whitespace().collect();
+    fields[index] = value;
+    match fen::from_fen_with_pieces(board.topology, Rc::clone(&board.pieces), &fields.join(" ")) {
+        Ok(mut edited) => {
+            edited.variant = Rc::clone(&board.variant);
+            *board = edited;
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Give up the castling rights whose king or rook is no longer where it started.
+fn drop_lost_castling(board: &mut Board) {
+    for colour in [Colour::White, Colour::Black] {
+        let row = match colour {
+            Colour::White => 0,
+            Colour::Black => board.topology.rows() - 1,
+        };
+        let king = board.find_king(colour).filter(|king| king.get_row() == row);
+        for kingside in [true, false] {
+            let Some(col) = board.castling.side(colour, kingside) else {
+                continue;
+            };
+            let rook = Position::on(board.topology, row, col);
+            let has_rook = board.get_piece(rook) == Some(Piece::new(PieceType::Rook, colour, rook));
+            if king.is_none() || !has_rook {
+                board.castling.set(colour, kingside, None);
+            }
+        }
+    }
+}
+
+/// Play Bughouse at one terminal. Moves are entered as the board followed by the
+/// move in SAN, e.g. `A e4` or `B N@f3`, whenever the player whose turn it is on
+/// that board is ready; `bpgn` prints the game so far.
+fn play_bughouse() {
+    let mut game = BughouseGame::new();
+
+    loop {
+        for (name, board) in BOARD_NAMES.iter().zip(&game.boards) {
+            println!("Board {}, {:?} to move:", name, board.turn);
+            board.display();
+        }
+        if let Some((board, outcome)) = game.outcome() {
+            println!("Board {}: {}", BOARD_NAMES[board], outcome);
+            println!("{}", game.to_bpgn());
+            break;
+        }
+        print!("> ");
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Failed to read line");
+        let input = input.trim();
+
+        if input == "exit" {
+            println!("Exiting...");
+            break;
+        }
+
+        if input == "bpgn" {
+            println!("{}", game.to_bpgn());
+            continue;
+        }
+
+        let Some((name, san)) = input.split_once(' ') else {
+            println!("Usage: <board> <move>, e.g. A e4");
+            continue;
+        };
+        let Some(board) = BOARD_NAMES.iter().position(|n| n.eq_ignore_ascii_case(&name.chars().next().unwrap_or(' '))) else {
+            println!("Unknown board {}", name);
+            continue;
+        };
+        match pgn::parse_san(&game.boards[board], san.trim()) {
+            Some((piece, to, promotion)) => {
+                game.play(board, piece, to, promotion.unwrap_or(PieceType::Queen));
+            }
+            None => println!("Move not valid!"),
+        }
         println!("\n");
     }
 }
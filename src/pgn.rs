@@ -0,0 +1,348 @@
+//src/pgn.rs
+
+use crate::{board::Board, fen::{self, FenError}, piece::{Colour, Piece, PieceType}, position::{Position, Topology}};
+
+/// A game read from a PGN file: its tag pairs and the SAN moves of the main line.
+#[derive(Debug, Clone, Default)]
+pub struct Game {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<String>,
+}
+
+impl Game {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+
+    /// `"1-0"`, `"0-1"`, `"1/2-1/2"` or `"*"` if the result is unknown.
+    pub fn result(&self) -> &str {
+        self.tag("Result").unwrap_or("*")
+    }
+
+    /// The position the game starts from: the `FEN` tag when `SetUp` is `1`, as for
+    /// handicap games, and otherwise the standard starting position.
+    pub fn start(&self) -> Result<Board, FenError> {
+        match (self.tag("SetUp"), self.tag("FEN")) {
+            (Some("1"), Some(fen)) => fen::from_fen(Topology::STANDARD, fen),
+            _ => Ok(Board::new(Board::STANDARD_SETUP)),
+        }
+    }
+
+    /// Write the game as PGN: the tags with `Result` last, then the numbered moves.
+    pub fn to_pgn(&self) -> String {
+        let mut text = String::new();
+        for (name, value) in self.tags.iter().filter(|(name, _)| name != "Result") {
+            text.push_str(&write_tag(name, value));
+        }
+        text.push_str(&write_tag("Result", self.result()));
+        text.push('\n');
+
+        let (mut number, mut turn) = self.start().map_or((1, Colour::White), |board| (board.fullmove_number, board.turn));
+        let mut tokens = Vec::new();
+        for (ply, san) in self.moves.iter().enumerate() {
+            match turn {
+                Colour::White => tokens.push(format!("{}. {}", number, san)),
+                Colour::Black if ply == 0 => tokens.push(format!("{}... {}", number, san)),
+                Colour::Black => tokens.push(san.clone()),
+            }
+            if turn == Colour::Black {
+                number += 1;
+            }
+            turn = !turn;
+        }
+        tokens.push(self.result().to_string());
+        text.push_str(&tokens.join(" "));
+        text.push('\n');
+        text
+    }
+}
+
+/// The `SetUp` and `FEN` tags for a game that starts from `board` rather than the
+/// standard starting position.
+pub fn setup_tags(board: &Board) -> Vec<(String, String)> {
+    vec![
+        ("SetUp".to_string(), "1".to_string()),
+        ("FEN".to_string(), fen::to_fen(board)),
+    ]
+}
+
+/// A tag pair line such as `[Event "Casual game"]`, with quotes and backslashes in
+/// the value escaped.
+pub fn write_tag(name: &str, value: &str) -> String {
+    format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Read every game in a PGN collection. Comments, variations and NAGs are skipped.
+pub fn read_games(text: &str) -> Vec<Game> {
+    let mut games = Vec::new();
+    let mut game = Game::default();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => {
+                if !game.moves.is_empty() {
+                    games.push(std::mem::take(&mut game));
+                }
+                let tag: String = chars.by_ref().take_while(|c| *c != '\n').collect();
+                if let Some(pair) = parse_tag(&tag) {
+                    game.tags.push(pair);
+                }
+            }
+            '{' => while chars.next().is_some_and(|c| c != '}') {},
+            ';' => while chars.next().is_some_and(|c| c != '\n') {},
+            '(' => {
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next() {
+                        Some('(') => depth += 1,
+                        Some(')') => depth -= 1,
+                        Some(_) => {}
+                        None => break,
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = String::from(c);
+                while let Some(next) = chars.peek() {
+                    if next.is_whitespace() || "{;([".contains(*next) {
+                        break;
+                    }
+                    token.push(chars.next().unwrap());
+                }
+
+                if ["1-0", "0-1", "1/2-1/2", "*"].contains(&token.as_str()) {
+                    if game.tag("Result").is_none() {
+                        game.tags.push(("Result".to_string(), token));
+                    }
+                    games.push(std::mem::take(&mut game));
+                    continue;
+                }
+
+                // Strip move numbers such as `12.` or `12...` and skip NAGs such as `$1`
+                let san = token.rsplit('.').next().unwrap_or_default();
+                if !san.is_empty() && !token.starts_with('$') {
+                    game.moves.push(san.to_string());
+                }
+            }
+        }
+    }
+
+    if !game.moves.is_empty() {
+        games.push(game);
+    }
+    games
+}
+
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let line = line.trim().trim_end_matches(']');
+    let (name, value) = line.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((name.to_string(), value.replace("\\\"", "\"").replace("\\\\", "\\")))
+}
+
+/// Write the move of `piece` to `to` on `board` in SAN, e.g. `Nbd2`, `exd6`,
+/// `e8=Q+`, `O-O` or `N@f3` for a drop. `promotion` is only used if a pawn reaches
+/// the last rank.
+pub fn to_san(board: &Board, piece: Piece, to: Position, promotion: PieceType) -> String {
+    let square = to.to_notation().to_lowercase();
+    let mut san = match piece {
+        Piece::Captured(piece_type, _) => format!("{}@{}", piece_type.to_string().to_uppercase(), square),
+        _ => match board.castling_side(piece, to) {
+            Some(true) => "O-O".to_string(),
+            Some(false) => "O-O-O".to_string(),
+            None => {
+                let from = piece.get_pos().to_notation().to_lowercase();
+                let (file, rank) = from.split_at(1);
+                let capture = if board.is_capture(piece, to) { "x" } else { "" };
+
+                if piece.get_piece_type() == PieceType::Pawn {
+                    let prefix = if capture.is_empty() { "" } else { file };
+                    let promotion = if to.is_back_rank(!piece.get_colour()) {
+                        format!("={}", promotion.to_string().to_uppercase())
+                    } else {
+                        String::new()
+                    };
+                    format!("{}{}{}{}", prefix, capture, square, promotion)
+                } else {
+                    // Name the file, rank or both if another such piece could move there too
+                    let others: Vec<String> = board.legal_moves().into_iter()
+                        .filter(|(other, other_to)| {
+                            *other_to == to && *other != piece && other.get_piece_type() == piece.get_piece_type()
+                        })
+                        .map(|(other, _)| other.get_pos().to_notation().to_lowercase())
+                        .collect();
+                    let disambiguation = if others.is_empty() {
+                        ""
+                    } else if others.iter().all(|other| !other.starts_with(file)) {
+                        file
+                    } else if others.iter().all(|other| &other[1..] != rank) {
+                        rank
+                    } else {
+                        &from
+                    };
+                    format!("{}{}{}{}", piece.get_piece_type().to_string().to_uppercase(), disambiguation, capture, square)
+                }
+            }
+        },
+    };
+
+    let after = board.clone().move_piece_with_promotion(piece, to, board.turn, promotion);
+    if after.variant.is_in_check(&after, after.turn) {
+        san.push(if after.legal_moves().is_empty() { '#' } else { '+' });
+    }
+    san
+}
+
+/// Find the piece, destination and promotion described by a SAN move such as
+/// `Nbd2`, `exd6`, `e8=Q+` or `O-O` for the side to move on `board`. A drop such
+/// as `N@f3` (or `@f3` for a pawn) gives a `Piece::Captured` from the pocket.
+pub fn parse_san(board: &Board, san: &str) -> Option<(Piece, Position, Option<PieceType>)> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+
+    if let Some((letter, to)) = san.split_once('@') {
+        let piece_type = match letter.chars().next() {
+            Some(c) => board.piece_type_from_letter(c)?,
+            None => PieceType::Pawn,
+        };
+        let piece = Piece::Captured(piece_type, board.turn);
+        let to = board.notation_to_position(to)?;
+        return piece.legal_moves(board).contains(&to).then_some((piece, to, None));
+    }
+
+    if san == "O-O" || san == "0-0" || san == "O-O-O" || san == "0-0-0" {
+        let king = board.get_piece(board.find_king(board.turn)?)?;
+        let to = board.castling_target(king, san.len() == 3)?;
+        return king.legal_moves(board).contains(&to).then_some((king, to, None));
+    }
+
+    let (san, promotion) = match san.char_indices().last()? {
+        (idx, c) if "QRBNACMK".contains(c) && idx >= 2 => {
+            (san[..idx].trim_end_matches('='), Some(PieceType::from_letter(c)?))
+        }
+        _ => (san, None),
+    };
+
+    let piece_type = match san.chars().next()? {
+        c if c.is_ascii_uppercase() => board.piece_type_from_letter(c)?,
+        _ => PieceType::Pawn,
+    };
+    let san = if piece_type == PieceType::Pawn { san } else { &san[1..] };
+
+    // The destination is the last file letter and everything after it, which can
+    // be a two digit rank on a large board
+    let (disambiguation, to) = san.split_at(san.rfind(|c: char| c.is_ascii_lowercase())?);
+    let to = board.notation_to_position(to)?;
+    let disambiguation = disambiguation.trim_end_matches('x');
+    let (file, rank) = disambiguation.split_at(disambiguation.find(|c: char| c.is_ascii_digit()).unwrap_or(disambiguation.len()));
+
+    let mut candidates = board.state().into_iter().filter(|piece| {
+        let from = piece.get_pos().to_notation().to_lowercase();
+        piece.get_colour() == board.turn
+            && piece.get_piece_type() == piece_type
+            && from.starts_with(file)
+            && (rank.is_empty() || &from[1..] == rank)
+            && piece.legal_moves(board).contains(&to)
+    });
+
+    match (candidates.next(), candidates.next()) {
+        (Some(piece), None) => Some((piece, to, promotion)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        fen::from_fen(Topology::STANDARD, fen).unwrap()
+    }
+
+    fn san(fen: &str, from: &str, to: &str) -> String {
+        let board = board(fen);
+        let piece = board.get_piece(board.notation_to_position(from).unwrap()).unwrap();
+        to_san(&board, piece, board.notation_to_position(to).unwrap(), PieceType::Queen)
+    }
+
+    #[test]
+    fn writes_san() {
+        assert_eq!(san(fen::STARTING_FEN, "G1", "F3"), "Nf3");
+        assert_eq!(san("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "B1", "D2"), "Nbd2");
+        assert_eq!(san("4k3/8/8/N7/8/8/8/N3K3 w - - 0 1", "A1", "B3"), "N1b3");
+        assert_eq!(san("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "E5", "D6"), "exd6");
+        assert_eq!(san("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "A7", "A8"), "a8=Q+");
+        assert_eq!(san("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "E1", "G1"), "O-O+");
+        assert_eq!(san("6k1/5ppp/8/8/8/8/8/R3K3 w Q - 0 1", "A1", "A8"), "Ra8#");
+    }
+
+    #[test]
+    fn san_round_trips() {
+        for fen in [
+            fen::STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "4k3/8/8/2PpP3/8/8/8/4K3 w - d6 0 1",
+        ] {
+            let board = board(fen);
+            for (piece, to) in board.legal_moves() {
+                let is_promotion = piece.get_piece_type() == PieceType::Pawn && to.is_back_rank(!piece.get_colour());
+                let promotions = if is_promotion { board.variant.promotions(&board) } else { vec![PieceType::Queen] };
+                for promotion in promotions {
+                    let san = to_san(&board, piece, to, promotion);
+                    assert_eq!(parse_san(&board, &san), Some((piece, to, is_promotion.then_some(promotion))), "{} in {}", san, fen);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_bad_san() {
+        let start = board(fen::STARTING_FEN);
+        for san in ["", "e5", "Nf4", "Ke2", "O-O", "Qd4", "z9", "N"] {
+            assert_eq!(parse_san(&start, san), None, "{}", san);
+        }
+        // Either knight could go there
+        assert_eq!(parse_san(&board("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1"), "Nd2"), None);
+    }
+
+    #[test]
+    fn reads_several_games() {
+        let text = r#"[Event "First"]
+[White "A \"quoted\" name"]
+[Result "1-0"]
+
+1. e4 {a comment} e5 2. Nf3 (2. f4 exf4) Nc6 $1 3. Bb5 ; the Spanish
+a6 1-0
+
+[Event "Second"]
+
+1. d4 d5 2. c4 1/2-1/2
+1. c4 *
+"#;
+        let games = read_games(text);
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].tag("Event"), Some("First"));
+        assert_eq!(games[0].tag("White"), Some("A \"quoted\" name"));
+        assert_eq!(games[0].moves, ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]);
+        assert_eq!(games[0].result(), "1-0");
+        assert_eq!(games[1].tag("Event"), Some("Second"));
+        assert_eq!(games[1].moves, ["d4", "d5", "c4"]);
+        assert_eq!(games[1].result(), "1/2-1/2");
+        assert_eq!(games[2].moves, ["c4"]);
+        assert_eq!(games[2].result(), "*");
+    }
+
+    #[test]
+    fn writes_pgn() {
+        let mut game = Game { tags: vec![("Result".to_string(), "0-1".to_string())], moves: vec!["f3".into(), "e5".into(), "g4".into(), "Qh4#".into()] };
+        assert_eq!(game.to_pgn(), "[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n");
+        assert_eq!(read_games(&game.to_pgn())[0].moves, game.moves);
+
+        // A game set up with black to move starts with `1...`
+        game.tags = setup_tags(&board("4k3/8/8/8/8/8/8/4K2R b K - 0 1"));
+        game.moves = vec!["Kd7".into(), "O-O".into()];
+        assert!(game.to_pgn().ends_with("\n1... Kd7 2. O-O *\n"));
+    }
+}
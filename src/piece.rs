@@ -2,7 +2,7 @@ use std::ops::Not;
 use std::{fmt};
 
 use crate::board::Board;
-use crate::position::{self, Position};
+use crate::position::Position;
 
 // src/piece.rs
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -13,6 +13,17 @@ pub enum PieceType {
     Bishop,
     King,
     Queen,
+    /// Moves as a bishop or a knight.
+    Archbishop,
+    /// Moves as a rook or a knight.
+    Chancellor,
+    /// Moves as a queen or a knight.
+    Amazon,
+    /// A piece defined in Betza notation, looked up by its upper-case letter in
+    /// `Board::pieces`.
+    Custom(char),
+    /// The neutral blocker of Duck chess, written `*`.
+    Duck,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -21,6 +32,31 @@ pub enum Colour {
     White
 }
 
+impl PieceType {
+    /// The piece for a SAN/FEN letter such as `N` or `n`. The fairy pieces are
+    /// `A` (archbishop), `C` (chancellor) and `M` (amazon), and `*` is the duck.
+    pub fn from_letter(letter: char) -> Option<PieceType> {
+        match letter.to_ascii_lowercase() {
+            'k' => Some(PieceType::King),
+            'q' => Some(PieceType::Queen),
+            'r' => Some(PieceType::Rook),
+            'b' => Some(PieceType::Bishop),
+            'n' => Some(PieceType::Knight),
+            'p' => Some(PieceType::Pawn),
+            'a' => Some(PieceType::Archbishop),
+            'c' => Some(PieceType::Chancellor),
+            'm' => Some(PieceType::Amazon),
+            '*' => Some(PieceType::Duck),
+            _ => None,
+        }
+    }
+
+    /// Is this one of the pieces of standard chess?
+    pub fn is_standard(&self) -> bool {
+        !matches!(self, PieceType::Archbishop | PieceType::Chancellor | PieceType::Amazon | PieceType::Custom(_) | PieceType::Duck)
+    }
+}
+
 impl Not for Colour {
     type Output = Colour;
 
@@ -41,7 +77,16 @@ pub enum Piece {
     Bishop(Colour, Position),
     Knight(Colour, Position),
     Pawn(Colour, Position),
-    Captured(Colour), //todo: need to update the logic so captured peices keep the type they were but have no pos
+    Archbishop(Colour, Position),
+    Chancellor(Colour, Position),
+    Amazon(Colour, Position),
+    Custom(char, Colour, Position),
+    /// A piece off the board: one that has been taken, or in Crazyhouse one held in
+    /// hand, which moves by being dropped onto an empty square.
+    Captured(PieceType, Colour),
+    /// The duck in Duck chess, which belongs to neither side. It blocks every piece
+    /// and cannot be captured, and is moved by `Board::play_duck_move`.
+    Duck(Position),
 }
 
 impl Piece {
@@ -53,6 +98,11 @@ impl Piece {
             PieceType::Bishop => Piece::Bishop(colour, position),
             PieceType::Knight => Piece::Knight(colour, position),
             PieceType::Pawn => Piece::Pawn(colour, position),
+            PieceType::Archbishop => Piece::Archbishop(colour, position),
+            PieceType::Chancellor => Piece::Chancellor(colour, position),
+            PieceType::Amazon => Piece::Amazon(colour, position),
+            PieceType::Custom(letter) => Piece::Custom(letter, colour, position),
+            PieceType::Duck => Piece::Duck(position),
         }
     }
 
@@ -64,7 +114,12 @@ impl Piece {
             Piece::Bishop(_, pos) => *pos,
             Piece::Knight(_, pos) => *pos,
             Piece::Pawn(_, pos) => *pos,
-            Piece::Captured(_) => panic!("Trying to get Position of captured piece!"),
+            Piece::Archbishop(_, pos) => *pos,
+            Piece::Chancellor(_, pos) => *pos,
+            Piece::Amazon(_, pos) => *pos,
+            Piece::Custom(_, _, pos) => *pos,
+            Piece::Captured(_, _) => panic!("Trying to get Position of captured piece!"),
+            Piece::Duck(pos) => *pos,
         }
     }
 
@@ -76,7 +131,12 @@ impl Piece {
             Piece::Bishop(_,_) => PieceType::Bishop,
             Piece::Knight(_,_) => PieceType::Knight,
             Piece::Pawn(_,_) => PieceType::Pawn,
-            Piece::Captured(_) => panic!("Trying to get Piece type of captured piece!"),
+            Piece::Archbishop(_,_) => PieceType::Archbishop,
+            Piece::Chancellor(_,_) => PieceType::Chancellor,
+            Piece::Amazon(_,_) => PieceType::Amazon,
+            Piece::Custom(letter, _, _) => PieceType::Custom(*letter),
+            Piece::Captured(piece_type, _) => *piece_type,
+            Piece::Duck(_) => PieceType::Duck,
         }
     }
 
@@ -88,24 +148,21 @@ impl Piece {
             Piece::Bishop(colour, _) => *colour,
             Piece::Knight(colour, _) => *colour,
             Piece::Pawn(colour, _) => *colour,
-            Piece::Captured(_) => panic!("Trying to get Colour of captured piece!"),
+            Piece::Archbishop(colour, _) => *colour,
+            Piece::Chancellor(colour, _) => *colour,
+            Piece::Amazon(colour, _) => *colour,
+            Piece::Custom(_, colour, _) => *colour,
+            Piece::Captured(_, colour) => *colour,
+            Piece::Duck(_) => panic!("The duck belongs to neither side!"),
         }
     }
 
+    /// Take the piece off the board, keeping its type and colour.
     pub fn set_captured(&mut self) {
-        let colour = match self {
-            Piece::King(col, _) |
-            Piece::Queen(col, _) |
-            Piece::Rook(col, _) |
-            Piece::Bishop(col, _) |
-            Piece::Knight(col, _) |
-            Piece::Pawn(col, _) => *col,
-
-            Piece::Captured(col) => {
-                return;
-            }
-        };
-        *self = Piece::Captured(colour);
+        if let Piece::Captured(_, _) = self {
+            return;
+        }
+        *self = Piece::Captured(self.get_piece_type(), self.get_colour());
     }
 
     pub fn set_pos(&mut self, new_pos: Position) {
@@ -116,22 +173,27 @@ impl Piece {
             Piece::Bishop(_, pos) => *pos = new_pos,
             Piece::Knight(_, pos) => *pos = new_pos,
             Piece::Pawn(_, pos) => *pos = new_pos,
-            Piece::Captured(_) => panic!("Captured pieces cannot have their position set"),
+            Piece::Archbishop(_, pos) => *pos = new_pos,
+            Piece::Chancellor(_, pos) => *pos = new_pos,
+            Piece::Amazon(_, pos) => *pos = new_pos,
+            Piece::Custom(_, _, pos) => *pos = new_pos,
+            Piece::Captured(_, _) => panic!("Captured pieces cannot have their position set"),
+            Piece::Duck(pos) => *pos = new_pos,
         }
     }
 
-    pub fn legal_moves(&self, board: &Board) -> Vec<Position> { //TODO return an array of Move types instead: Move::Piece(pos, to_pos)
+    /// Moves that obey how the piece moves, without checking whether they leave
+    /// the mover's own king in check.
+    pub fn pseudo_legal_moves(&self, board: &Board) -> Vec<Position> { //TODO return an array of Move types instead: Move::Piece(pos, to_pos)
         let mut result = Vec::new();
 
         match *self {
             Self::Pawn(ally_colour, pos) => {
                 let up: Position = pos.pawn_up(ally_colour);
                 let double_up: Position = up.pawn_up(ally_colour);
-                let up_left = up.next_left();
-                let up_right = up.next_right();
 
                 if double_up.is_on_board()
-                    && Position::is_starting_pawn(&self.get_pos(), ally_colour)
+                    && board.variant.can_double_step(self.get_pos(), ally_colour)
                     && board.has_no_piece(up)
                     && board.has_no_piece(double_up)
                 {
@@ -142,119 +204,129 @@ impl Piece {
                     result.push(up)
                 }
 
-                if up_left.is_on_board() && board.has_enemy_piece(pos, ally_colour) {
-                    result.push(up_left)
-                }
-
-                if up_right.is_on_board() && board.has_enemy_piece(pos, ally_colour) {
-                    result.push(up_right)
+                for p in pos.pawn_attacks(ally_colour) {
+                    // In Duck chess the duck may be sitting on the en passant square
+                    let is_en_passant = board.turn == ally_colour && board.en_passant == Some(p) && board.has_no_piece(p);
+                    if board.has_enemy_piece(p, ally_colour) || is_en_passant {
+                        result.push(p)
+                    }
                 }
-
-                //TODO implement en passant
             }
 
             Self::King(ally_colour, pos) => {
-                for p in &[
-                    pos.next_above(),
-                    pos.next_below(),
-                    pos.next_left(),
-                    pos.next_right(),
-                    pos.next_above().next_left(),
-                    pos.next_above().next_right(),
-                    pos.next_below().next_left(),
-                    pos.next_below().next_right(),
-                ] {
-                    if p.is_on_board() && !board.has_friendly_piece(*p, ally_colour) {
-                        result.push(*p)
+                for p in pos.neighbours() {
+                    if !board.has_friendly_piece(p, ally_colour) {
+                        result.push(p)
                     }
                 }
-                //TODO: implement kingside and queenside rook
+                result.extend(board.castling_moves(*self));
             },
 
             Self::Queen(ally_colour, pos) => {
-                for p in &[
-                    Position::max_travel(board, pos, ally_colour, "UP"),
-                    Position::max_travel(board, pos, ally_colour, "DOWN"),
-                    Position::max_travel(board, pos, ally_colour, "LEFT"),
-                    Position::max_travel(board, pos, ally_colour, "RIGHT"),
-                ] {
-                    for tile in Position::orthogonals_to(&pos, *p) {
-                        if p.is_on_board() && !board.has_friendly_piece(*p, ally_colour) {
-                            result.push(*p)
-                        }
-                    }
-                }
-
-                for p in &[
-                    Position::max_travel(board, pos, ally_colour, "NE"),
-                    Position::max_travel(board, pos, ally_colour, "NW"),
-                    Position::max_travel(board, pos, ally_colour, "SE"),
-                    Position::max_travel(board, pos, ally_colour, "SW"),
-                ] {
-                    for tile in Position::diagonals_to(&pos, *p) {
-                        if p.is_on_board() && !board.has_friendly_piece(*p, ally_colour) {
-                            result.push(*p)
-                        }
-                    }
-                }
-
-                //TODO: Implement queen movement for horse
+                result.extend(Self::orthogonal_moves(board, pos, ally_colour));
+                result.extend(Self::diagonal_moves(board, pos, ally_colour));
             },
 
             Self::Rook(ally_colour, pos) => {
-                for p in &[
-                    Position::max_travel(board, pos, ally_colour, "UP"),
-                    Position::max_travel(board, pos, ally_colour, "DOWN"),
-                    Position::max_travel(board, pos, ally_colour, "LEFT"),
-                    Position::max_travel(board, pos, ally_colour, "RIGHT"),
-                ] {
-                    for tile in Position::orthogonals_to(&pos, *p) {
-                        if p.is_on_board() && !board.has_friendly_piece(*p, ally_colour) {
-                            result.push(*p)
-                        }
-                    }
-                }
+                result.extend(Self::orthogonal_moves(board, pos, ally_colour));
             },
 
             Self::Bishop(ally_colour, pos) => {
-                for p in &[
-                    Position::max_travel(board, pos, ally_colour, "NE"),
-                    Position::max_travel(board, pos, ally_colour, "NW"),
-                    Position::max_travel(board, pos, ally_colour, "SE"),
-                    Position::max_travel(board, pos, ally_colour, "SW"),
-                ] {
-                    for tile in Position::diagonals_to(&pos, *p) {
-                        if p.is_on_board() && !board.has_friendly_piece(*p, ally_colour) {
-                            result.push(*p)
-                        }
-                    }
-                }   
+                result.extend(Self::diagonal_moves(board, pos, ally_colour));
             },
-            
+
             Self::Knight(ally_colour, pos) => {
-                let potential_knight_moves = vec![
-                    Position::new(pos.get_row() + 2, pos.get_col() + 1), // Move 2 up, 1 right
-                    Position::new(pos.get_row() + 2, pos.get_col() - 1), // Move 2 up, 1 left
-                    Position::new(pos.get_row() - 2, pos.get_col() + 1), // Move 2 down, 1 right
-                    Position::new(pos.get_row() - 2, pos.get_col() - 1), // Move 2 down, 1 left
-                    Position::new(pos.get_row() + 1, pos.get_col() + 2), // Move 1 up, 2 right
-                    Position::new(pos.get_row() + 1, pos.get_col() - 2), // Move 1 up, 2 left
-                    Position::new(pos.get_row() - 1, pos.get_col() + 2), // Move 1 down, 2 right
-                    Position::new(pos.get_row() - 1, pos.get_col() - 2), // Move 1 down, 2 left
-                ];
-
-                for p in potential_knight_moves {
-                    if p.is_on_board() && !board.has_enemy_piece(p, ally_colour) {
-                        result.push(p);
-                    }
-                }
+                result.extend(Self::knight_moves(board, pos, ally_colour));
+            },
+
+            Self::Archbishop(ally_colour, pos) => {
+                result.extend(Self::diagonal_moves(board, pos, ally_colour));
+                result.extend(Self::knight_moves(board, pos, ally_colour));
+            },
+
+            Self::Chancellor(ally_colour, pos) => {
+                result.extend(Self::orthogonal_moves(board, pos, ally_colour));
+                result.extend(Self::knight_moves(board, pos, ally_colour));
+            },
+
+            Self::Amazon(ally_colour, pos) => {
+                result.extend(Self::orthogonal_moves(board, pos, ally_colour));
+                result.extend(Self::diagonal_moves(board, pos, ally_colour));
+                result.extend(Self::knight_moves(board, pos, ally_colour));
+            },
+
+            Self::Custom(letter, ally_colour, pos) => {
+                let definition = board.piece_definition(letter).expect("Custom piece has no definition");
+                result.extend(definition.betza.moves(board, pos, ally_colour));
             },
 
-            Self::Captured(_) => panic!("Caputed piece has no legal moves")
+            Self::Captured(_, _) => panic!("Caputed piece has no legal moves"),
+
+            // The duck is only ever moved after another piece, see `Board::play_duck_move`
+            Self::Duck(_) => {},
         };
 
+        // Where the board wraps around a slider can reach the same square going either way round
+        result.sort();
+        result.dedup();
         result
     }
+
+    /// Rook moves from `pos`.
+    fn orthogonal_moves(board: &Board, pos: Position, ally_colour: Colour) -> Vec<Position> {
+        ["UP", "DOWN", "LEFT", "RIGHT"].into_iter()
+            .flat_map(|direction| Position::travel(board, pos, ally_colour, direction))
+            .collect()
+    }
+
+    /// Bishop moves from `pos`.
+    fn diagonal_moves(board: &Board, pos: Position, ally_colour: Colour) -> Vec<Position> {
+        ["NE", "NW", "SE", "SW"].into_iter()
+            .flat_map(|direction| Position::travel(board, pos, ally_colour, direction))
+            .collect()
+    }
+
+    /// Knight moves from `pos`.
+    fn knight_moves(board: &Board, pos: Position, ally_colour: Colour) -> Vec<Position> {
+        let potential_knight_moves = vec![
+            pos.offset(2, 1), // Move 2 up, 1 right
+            pos.offset(2, -1), // Move 2 up, 1 left
+            pos.offset(-2, 1), // Move 2 down, 1 right
+            pos.offset(-2, -1), // Move 2 down, 1 left
+            pos.offset(1, 2), // Move 1 up, 2 right
+            pos.offset(1, -2), // Move 1 up, 2 left
+            pos.offset(-1, 2), // Move 1 down, 2 right
+            pos.offset(-1, -2), // Move 1 down, 2 left
+        ];
+
+        potential_knight_moves.into_iter()
+            .filter(|p| p.is_on_board() && !board.has_friendly_piece(*p, ally_colour))
+            .collect()
+    }
+
+    /// This piece's share of `Board::legal_moves`, so the variant's rules apply.
+    pub fn legal_moves(&self, board: &Board) -> Vec<Position> {
+        board.legal_moves()
+            .into_iter()
+            .filter(|(piece, _)| piece == self)
+            .map(|(_, to)| to)
+            .collect()
+    }
+
+    /// Squares this piece attacks, whether or not they are occupied. Pawns only
+    /// attack diagonally, kings never attack by castling and custom pieces only
+    /// attack where they can capture.
+    pub fn attacks(&self, board: &Board) -> Vec<Position> {
+        match *self {
+            Self::Pawn(ally_colour, pos) => pos.pawn_attacks(ally_colour),
+            Self::King(_, pos) => pos.neighbours(),
+            Self::Custom(letter, ally_colour, pos) => {
+                let definition = board.piece_definition(letter).expect("Custom piece has no definition");
+                definition.betza.attacks(board, pos, ally_colour)
+            }
+            _ => self.pseudo_legal_moves(board),
+        }
+    }
 }
 
 impl fmt::Display for PieceType {
@@ -266,12 +338,20 @@ impl fmt::Display for PieceType {
             PieceType::Bishop => write!(f, "b"),
             PieceType::Knight => write!(f, "n"),
             PieceType::Pawn => write!(f, "p"),
+            PieceType::Archbishop => write!(f, "a"),
+            PieceType::Chancellor => write!(f, "c"),
+            PieceType::Amazon => write!(f, "m"),
+            PieceType::Custom(letter) => write!(f, "{}", letter.to_ascii_lowercase()),
+            PieceType::Duck => write!(f, "*"),
         }
     }
 }
 
 impl fmt::Display for Piece {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Piece::Duck(_) = self {
+            return write!(f, "*");
+        }
 
         let symbol = match (self.get_piece_type(), self.get_colour()) {
             (PieceType::Pawn, Colour::White) => "♙",
@@ -291,7 +371,60 @@ impl fmt::Display for Piece {
 
             (PieceType::Queen, Colour::White) => "♕",
             (PieceType::Queen, Colour::Black) => "♛",
+
+            (PieceType::Archbishop, Colour::White) => "\u{1FA50}", // knight-bishop
+            (PieceType::Archbishop, Colour::Black) => "\u{1FA53}",
+
+            (PieceType::Chancellor, Colour::White) => "\u{1FA4F}", // knight-rook
+            (PieceType::Chancellor, Colour::Black) => "\u{1FA52}",
+
+            (PieceType::Amazon, Colour::White) => "\u{1FA4E}", // knight-queen
+            (PieceType::Amazon, Colour::Black) => "\u{1FA51}",
+
+            // Custom pieces have no symbol of their own, so show their letter
+            (PieceType::Custom(letter), Colour::White) => return write!(f, "{}", letter),
+            (PieceType::Custom(letter), Colour::Black) => return write!(f, "{}", letter.to_ascii_lowercase()),
+
+            (PieceType::Duck, _) => unreachable!("the duck has no colour"),
         };
         write!(f, "{}", symbol)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fen, position::{A1, C8, D4, Topology}};
+
+    /// Moves of the white piece on D4 when it is alone with the kings.
+    fn moves_from_d4(letter: char) -> Vec<Position> {
+        let board = fen::from_fen(Topology::STANDARD, &format!("k7/8/8/8/3{}4/8/7K/8 w - - 0 1", letter)).unwrap();
+        board.get_piece(D4).unwrap().legal_moves(&board)
+    }
+
+    #[test]
+    fn fairy_pieces_add_the_knight_move() {
+        let knight = moves_from_d4('N');
+        for (letter, without_knight) in [('A', 'B'), ('C', 'R'), ('M', 'Q')] {
+            let mut expected = moves_from_d4(without_knight);
+            expected.extend(&knight);
+            expected.sort();
+            assert_eq!(moves_from_d4(letter), expected, "{}", letter);
+        }
+        assert_eq!(moves_from_d4('A').len(), 13 + 8);
+        assert_eq!(moves_from_d4('C').len(), 14 + 8);
+        assert_eq!(moves_from_d4('M').len(), 27 + 8);
+    }
+
+    #[test]
+    fn fairy_letters_round_trip() {
+        let fen = "acm1k3/8/8/8/8/8/8/ACM1K3 w - - 0 1";
+        let board = fen::from_fen(Topology::STANDARD, fen).unwrap();
+        assert_eq!(board.get_piece(A1), Some(Piece::new(PieceType::Archbishop, Colour::White, A1)));
+        assert_eq!(board.get_piece(C8), Some(Piece::new(PieceType::Amazon, Colour::Black, C8)));
+        assert_eq!(fen::to_fen(&board), fen);
+        for letter in ['A', 'C', 'M'] {
+            assert_eq!(PieceType::from_letter(letter).unwrap().to_string().to_uppercase(), letter.to_string());
+        }
+    }
+}
@@ -1,7 +1,6 @@
-use std::cmp::max;
-
-use crate::{board::Board, piece::Colour};
+use crate::{board::{Board, CastlingRights}, piece::Colour};
 
+// Squares of the standard board. Other sizes go through `Position::on`.
 
 pub const A1: Position = Position::new(0, 0);
 pub const A2: Position = Position::new(1, 0);
@@ -75,6 +74,7 @@ pub const H6: Position = Position::new(5, 7);
 pub const H7: Position = Position::new(6, 7);
 pub const H8: Position = Position::new(7, 7);
 
+/// Look up a square of the standard board by its upper-case name.
 pub fn interpret_position(input: &str) -> Option<Position> {
     match input {
         "A1" => Some(A1),
@@ -146,15 +146,127 @@ pub fn interpret_position(input: &str) -> Option<Position> {
 }
 
 
+/// The shape of the board a position lives on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Topology {
+    /// An ordinary board of `ranks` rows and `files` columns, 8x8 for standard chess.
+    Rectangular { ranks: i32, files: i32 },
+    /// Concentric rings cut into sectors, as in Byzantine (circular) chess. Rows are
+    /// rings, numbered from the outside in, and columns are sectors, which wrap around
+    /// so the last sector is next to the first.
+    Radial { rings: i32, sectors: i32 },
+    /// A rectangular board rolled into a tube, so the first and last files are next
+    /// to each other, as in cylinder chess.
+    Cylinder { ranks: i32, files: i32 },
+    /// A rectangular board where the ranks wrap around as well as the files, so the
+    /// first and last ranks are next to each other too.
+    Torus { ranks: i32, files: i32 },
+}
+
+impl Topology {
+    pub const STANDARD: Topology = Topology::Rectangular { ranks: 8, files: 8 };
+
+    /// 5x5 Gardner minichess.
+    pub const GARDNER: Topology = Topology::Rectangular { ranks: 5, files: 5 };
+
+    /// 6x6 Los Alamos chess.
+    pub const LOS_ALAMOS: Topology = Topology::Rectangular { ranks: 6, files: 6 };
+
+    /// 10 files by 8 ranks, as in Capablanca chess.
+    pub const CAPABLANCA: Topology = Topology::Rectangular { ranks: 8, files: 10 };
+
+    /// Four rings of sixteen sectors, the same 64 cells as the square board.
+    pub const RADIAL: Topology = Topology::Radial { rings: 4, sectors: 16 };
+
+    /// The standard board with the A and H files joined.
+    pub const CYLINDER: Topology = Topology::Cylinder { ranks: 8, files: 8 };
+
+    /// The standard board with the A and H files joined and the first and eighth ranks too.
+    pub const TORUS: Topology = Topology::Torus { ranks: 8, files: 8 };
+
+    pub const fn rows(&self) -> i32 {
+        match *self {
+            Topology::Rectangular { ranks, .. } | Topology::Cylinder { ranks, .. } | Topology::Torus { ranks, .. } => ranks,
+            Topology::Radial { rings, .. } => rings,
+        }
+    }
+
+    pub const fn cols(&self) -> i32 {
+        match *self {
+            Topology::Rectangular { files, .. } | Topology::Cylinder { files, .. } | Topology::Torus { files, .. } => files,
+            Topology::Radial { sectors, .. } => sectors,
+        }
+    }
+
+    /// Is the last row next to the first?
+    pub const fn wraps_rows(&self) -> bool {
+        matches!(self, Topology::Torus { .. })
+    }
+
+    /// Is the last column next to the first?
+    pub const fn wraps_cols(&self) -> bool {
+        !matches!(self, Topology::Rectangular { .. })
+    }
+}
+
+/// `delta` steps along an axis of `size` cells, taken the short way round if the
+/// axis wraps.
+fn shortest(delta: i32, size: i32, wraps: bool) -> i32 {
+    if !wraps {
+        return delta;
+    }
+    let delta = delta.rem_euclid(size);
+    if delta > size / 2 { delta - size } else { delta }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Position {
     row: i32,
     col: i32,
+    topology: Topology,
 }
 
 impl Position {
+    /// A position on the standard 8x8 board.
     pub const fn new(row: i32, col: i32) -> Self {
-        Self { row, col }
+        Self { row, col, topology: Topology::STANDARD }
+    }
+
+    /// A position on a board of the given topology. Rows and columns that wrap
+    /// around (see `Topology::wraps_rows`) are brought back onto the board.
+    pub const fn on(topology: Topology, row: i32, col: i32) -> Self {
+        let row = if topology.wraps_rows() { row.rem_euclid(topology.rows()) } else { row };
+        let col = if topology.wraps_cols() { col.rem_euclid(topology.cols()) } else { col };
+        Self { row, col, topology }
+    }
+
+    #[inline]
+    pub fn get_topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Columns are lettered from `A` and rows numbered from `1`, so on the standard
+    /// board this is the usual `A1`..`H8` (and `A1`..`J10` on a 10x10 board). On a radial board sectors play the part
+    /// of files and rings of ranks: `A1`..`P1` run around the outer ring and `A4`
+    /// is the innermost cell of the first sector.
+    pub fn to_notation(&self) -> String {
+        let column_letter = (self.col + 65) as u8 as char; // A=65 in ASCII
+        format!("{}{}", column_letter, self.row + 1)
+    }
+
+    /// The inverse of `to_notation`, ignoring the case of the letter. Returns
+    /// `None` for anything that is not a cell of the given board.
+    pub fn from_notation(topology: Topology, notation: &str) -> Option<Position> {
+        let mut chars = notation.chars();
+        let column_letter = chars.next()?.to_ascii_uppercase();
+        let row_number: i32 = chars.as_str().parse().ok()?;
+        if !column_letter.is_ascii_uppercase() {
+            return None;
+        }
+
+        let col = column_letter as i32 - 65;
+        let pos = Position::on(topology, row_number - 1, col);
+        (pos.is_on_board() && pos.get_col() == col).then_some(pos)
     }
 
     #[inline]
@@ -164,7 +276,7 @@ impl Position {
 
     #[inline]
     pub fn is_off_board(&self) -> bool {
-        self.row < 0 || self.row > 7 || self.col < 0 || self.col > 7
+        self.row < 0 || self.row >= self.topology.rows() || self.col < 0 || self.col >= self.topology.cols()
     }
 
     #[inline]
@@ -177,26 +289,38 @@ impl Position {
         self.col
     }
 
+    /// Rows from here to `other`, the short way round if rows wrap.
+    #[inline]
+    fn row_delta(&self, other: Self) -> i32 {
+        shortest(other.row - self.row, self.topology.rows(), self.topology.wraps_rows())
+    }
+
+    /// Columns from here to `other`, the short way round if columns wrap.
+    #[inline]
+    fn col_delta(&self, other: Self) -> i32 {
+        shortest(other.col - self.col, self.topology.cols(), self.topology.wraps_cols())
+    }
+
     #[inline]
     pub fn is_diagonal_to(&self, other: Self) -> bool {
         // Algorithm for determining whether or not two squares are diagonal
         // https://math.stackexchange.com/questions/1194565/how-to-know-if-two-points-are-diagonally-aligned
-        (self.col - other.col).abs() == (self.row - other.row).abs()
+        self.col_delta(other).abs() == self.row_delta(other).abs()
     }
 
     #[inline]
     fn diagonal_distance(&self, other: Self) -> i32 {
-        (self.col - other.col).abs()
+        self.col_delta(other).abs()
     }
 
     #[inline]
     pub fn is_orthogonal_to(&self, other: Self) -> bool {
-        (self.col == other.col) || (self.row == other.row)
+        self.col_delta(other) == 0 || self.row_delta(other) == 0
     }
 
     #[inline]
     fn orthogonal_distance(&self, other: Self) -> i32 {
-        (self.col - other.col).abs() + (self.row - other.row).abs()
+        self.col_delta(other).abs() + self.row_delta(other).abs()
     }
 
     #[inline]
@@ -239,10 +363,11 @@ impl Position {
     /// method!
     #[inline]
     pub fn next_below(&self) -> Self {
-        Self::new(self.row - 1, self.col)
+        Self::on(self.topology, self.row - 1, self.col)
     }
 
-    /// Get the position directly above this position.
+    /// Get the position directly above this position. On a
+    /// radial board this is one ring closer to the centre.
     ///
     /// IMPORTANT NOTE: This will NOT check for positions
     /// off of the board! You could easily get an invalid
@@ -250,7 +375,7 @@ impl Position {
     /// method!
     #[inline]
     pub fn next_above(&self) -> Self {
-        Self::new(self.row + 1, self.col)
+        Self::on(self.topology, self.row + 1, self.col)
     }
 
     /// Get the next square upwards from a respective player's
@@ -260,11 +385,23 @@ impl Position {
     /// off of the board! You could easily get an invalid
     /// position if you do not check with the `is_on_board`
     /// method!
+    /// On a radial board pawns run around the rings towards the enemy's
+    /// starting sectors, so which way is up depends on which half of
+    /// the circle the pawn is in.
     #[inline]
     pub fn pawn_up(&self, ally_color: Colour) -> Self {
-        match ally_color {
-            Colour::White => self.next_below(),
-            Colour::Black => self.next_above(),
+        match self.topology {
+            Topology::Rectangular { .. } | Topology::Cylinder { .. } | Topology::Torus { .. } => match ally_color {
+                Colour::White => self.next_above(),
+                Colour::Black => self.next_below(),
+            },
+            Topology::Radial { sectors, .. } => {
+                if (self.col < sectors / 2) == (ally_color == Colour::White) {
+                    self.next_right()
+                } else {
+                    self.next_left()
+                }
+            }
         }
     }
 
@@ -273,7 +410,19 @@ impl Position {
         self.pawn_up(!ally_color)
     }
 
-    /// Get the position directly left of this position.
+    /// The squares diagonally in front of a pawn, which it attacks. On a
+    /// radial board these are on the rings either side.
+    pub fn pawn_attacks(&self, ally_color: Colour) -> Vec<Self> {
+        let up = self.pawn_up(ally_color);
+        let sides = match self.topology {
+            Topology::Rectangular { .. } | Topology::Cylinder { .. } | Topology::Torus { .. } => [up.next_left(), up.next_right()],
+            Topology::Radial { .. } => [up.next_above(), up.next_below()],
+        };
+        sides.into_iter().filter(|p| p.is_on_board()).collect()
+    }
+
+    /// Get the position directly left of this position. On a
+    /// radial board this wraps around to the last sector.
     ///
     /// IMPORTANT NOTE: This will NOT check for positions
     /// off of the board! You could easily get an invalid
@@ -281,10 +430,11 @@ impl Position {
     /// method!
     #[inline]
     pub fn next_left(&self) -> Self {
-        Self::new(self.row, self.col - 1)
+        Self::on(self.topology, self.row, self.col - 1)
     }
 
-    /// Get the position directly right of this position.
+    /// Get the position directly right of this position. On a
+    /// radial board this wraps around to the first sector.
     ///
     /// IMPORTANT NOTE: This will NOT check for positions
     /// off of the board! You could easily get an invalid
@@ -292,41 +442,89 @@ impl Position {
     /// method!
     #[inline]
     pub fn next_right(&self) -> Self {
-        Self::new(self.row, self.col + 1)
+        Self::on(self.topology, self.row, self.col + 1)
     }
 
+    /// The (up to) eight positions surrounding this one, i.e. where a king can step.
+    pub fn neighbours(&self) -> Vec<Self> {
+        [
+            self.next_above(),
+            self.next_below(),
+            self.next_left(),
+            self.next_right(),
+            self.next_above().next_left(),
+            self.next_above().next_right(),
+            self.next_below().next_left(),
+            self.next_below().next_right(),
+        ]
+        .into_iter()
+        .filter(|p| p.is_on_board())
+        .collect()
+    }
+
+    /// Can a pawn of `color` here make a double step? Pawns on boards of fewer than
+    /// eight ranks, like Gardner and Los Alamos, never do.
     #[inline]
     pub fn is_starting_pawn(&self, color: Colour) -> bool {
-        match color {
-            Colour::White => self.row == 1,
-            Colour::Black => self.row == 6,
+        let ranks = self.topology.rows();
+        match (self.topology, color) {
+            (Topology::Radial { sectors, .. }, Colour::White) => self.col == 1 || self.col == sectors - 2,
+            (Topology::Radial { sectors, .. }, Colour::Black) => {
+                self.col == sectors / 2 - 2 || self.col == sectors / 2 + 1
+            }
+            _ if ranks < 8 => false,
+            (_, Colour::White) => self.row == 1,
+            (_, Colour::Black) => self.row == ranks - 2,
+        }
+    }
+
+    /// Is this on `color`'s first rank? On a radial board these are the two
+    /// sectors `color`'s pieces start on, where the enemy's pawns promote.
+    #[inline]
+    pub fn is_back_rank(&self, color: Colour) -> bool {
+        match (self.topology, color) {
+            (Topology::Radial { sectors, .. }, Colour::White) => self.col == 0 || self.col == sectors - 1,
+            (Topology::Radial { sectors, .. }, Colour::Black) => {
+                self.col == sectors / 2 - 1 || self.col == sectors / 2
+            }
+            (_, Colour::White) => self.row == 0,
+            (_, Colour::Black) => self.row == self.topology.rows() - 1,
         }
     }
 
-    /// Is this the starting position of the kingside rook?
+    /// Is this where `color`'s kingside rook has to be for them to castle kingside?
+    /// In Chess960 that can be any file right of the king.
+    #[inline]
+    pub fn is_kingside_rook(&self, castling: &CastlingRights, color: Colour) -> bool {
+        self.is_back_rank(color) && castling.kingside(color) == Some(self.col)
+    }
+
+    /// Is this where `color`'s queenside rook has to be for them to castle queenside?
     #[inline]
-    pub fn is_kingside_rook(&self) -> bool {
-        (self.row == 0 || self.row == 7) && self.col == 7
+    pub fn is_queenside_rook(&self, castling: &CastlingRights, color: Colour) -> bool {
+        self.is_back_rank(color) && castling.queenside(color) == Some(self.col)
     }
 
-    /// Is this the starting position of the queenside rook?
+    /// Get the position `drow` rows and `dcol` columns away, wrapping
+    /// around the sectors on a radial board.
+    ///
+    /// IMPORTANT NOTE: This will NOT check for positions
+    /// off of the board! You could easily get an invalid
+    /// position if you do not check with the `is_on_board`
+    /// method!
     #[inline]
-    pub fn is_queenside_rook(&self) -> bool {
-        (self.row == 0 || self.row == 7) && self.col == 0
+    pub fn offset(&self, drow: i32, dcol: i32) -> Self {
+        Self::on(self.topology, self.row + drow, self.col + dcol)
     }
 
     #[inline]
     fn add_row(&self, drow: i32) -> Self {
-        let mut result = *self;
-        result.row += drow;
-        result
+        self.offset(drow, 0)
     }
 
     #[inline]
     fn add_col(&self, dcol: i32) -> Self {
-        let mut result = *self;
-        result.col += dcol;
-        result
+        self.offset(0, dcol)
     }
 
     /// Returns a vector of positions along the diagonal from `self` to `to`.
@@ -338,19 +536,8 @@ impl Position {
             return Vec::new();
         }
 
-        let row_step;
-        let col_step;
-        if self.is_left_of(to) {
-            col_step = 1;
-        } else {
-            col_step = -1;
-        }
-
-        if self.is_below(to) {
-            row_step = 1;
-        } else {
-            row_step = -1;
-        }
+        let col_step = self.col_delta(to).signum();
+        let row_step = self.row_delta(to).signum();
 
         let mut acc = *self;
         let mut result = Vec::new();
@@ -366,17 +553,8 @@ impl Position {
         if !self.is_orthogonal_to(to) {
             return Vec::new();
         }
-        let mut row_step = 0;
-        let mut col_step = 0;
-        if self.is_left_of(to) {
-            col_step = 1;
-        } else if self.is_right_of(to) {
-            col_step = -1;
-        } else if self.is_above(to) {
-            row_step = -1;
-        } else if self.is_below(to) {
-            row_step = 1;
-        }
+        let row_step = self.row_delta(to).signum();
+        let col_step = self.col_delta(to).signum();
 
         let mut acc = *self;
         let mut result = Vec::new();
@@ -391,70 +569,94 @@ impl Position {
 
     #[inline]
     pub fn is_knight_move(&self, other: Self) -> bool {
-        (self.row - other.row).abs() == 2 && (self.col - other.col).abs() == 1
-            || (self.row - other.row).abs() == 1 && (self.col - other.col).abs() == 2
+        let (drow, dcol) = (self.row_delta(other).abs(), self.col_delta(other).abs());
+        drow == 2 && dcol == 1 || drow == 1 && dcol == 2
     }
 
-    //The max distance you can travel in a given direction up to and including a colision with an enemy piece
-    pub fn max_travel(board: &Board, pos: Position, ally_colour: Colour, direction: &str) -> Position{
-        let to: Position = match direction {
-            "UP" => Position::new(Board::SIZE as i32, pos.get_col()),
-            "DOWN" => Position::new(0, pos.get_col()),
-            "LEFT" => Position::new(pos.get_row(), 0),
-            "RIGHT" => Position::new(pos.get_row(), Board::SIZE as i32),
-            "NE" => Position::new(pos.get_row(), Board::SIZE as i32),
-            "NW" => Position::new(Board::SIZE as i32, pos.get_col()),
-            "SE" => Position::new(Board::SIZE as i32, pos.get_col()),
-            "SW" => Position::new(Board::SIZE as i32, pos.get_col()),
-            _ => pos,
+    /// Every square you can travel to in a given direction, up to and including a
+    /// collision with an enemy piece. On a radial board a rook moving around a ring
+    /// can go all the way round to the square behind where it started.
+    pub fn travel(board: &Board, pos: Position, ally_colour: Colour, direction: &str) -> Vec<Position> {
+        let step = |p: Position| match direction {
+            "UP" => p.next_above(),
+            "DOWN" => p.next_below(),
+            "LEFT" => p.next_left(),
+            "RIGHT" => p.next_right(),
+            "NE" => p.next_above().next_right(),
+            "NW" => p.next_above().next_left(),
+            "SE" => p.next_below().next_right(),
+            "SW" => p.next_below().next_left(),
+            _ => p,
         };
 
-        let mut max_travel = 0;
-        if pos.row == to.row || pos.col == to.col {
-            //orthogonal:
-            for p in Position::orthogonals_to(&pos, to) {
-                if board.has_no_piece(p) {
-                    max_travel += 1
-                }
-                //allow move onto enemy piece but then break loop
-                if board.has_enemy_piece(p, ally_colour) {
-                    max_travel += 1;
-                    break
-                }
-                if board.has_piece(p) {
-                    break
-                }
+        let mut result = Vec::new();
+        let mut next = step(pos);
+        while next != pos && next.is_on_board() && !board.has_friendly_piece(next, ally_colour) {
+            result.push(next);
+            //allow move onto enemy piece but then break loop
+            if board.has_enemy_piece(next, ally_colour) {
+                break
             }
+            next = step(next);
         }
-        else if (pos.row - to.row).abs() == (pos.col - to.col).abs() {
-            //diagonal:
-            for p in Position::diagonals_to(&pos, to) {
-                if board.has_no_piece(p) {
-                    max_travel += 1
-                }
-                //allow move onto enemy piece but then break loop
-                if board.has_enemy_piece(p, ally_colour) {
-                    max_travel += 1;
-                    break
-                }
-                if board.has_piece(p) {
-                    break
+        result
+    }
+
+    //The max distance you can travel in a given direction up to and including a colision with an enemy piece
+    pub fn max_travel(board: &Board, pos: Position, ally_colour: Colour, direction: &str) -> Position{
+        Self::travel(board, pos, ally_colour, direction).last().copied().unwrap_or(pos)
+    }
+    
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notation_round_trips() {
+        for topology in [Topology::STANDARD, Topology::RADIAL] {
+            for row in 0..topology.rows() {
+                for col in 0..topology.cols() {
+                    let pos = Position::on(topology, row, col);
+                    let notation = pos.to_notation();
+                    assert_eq!(Position::from_notation(topology, &notation), Some(pos), "{}", notation);
+                    assert_eq!(Position::from_notation(topology, &notation.to_lowercase()), Some(pos));
                 }
             }
         }
-        let max_travel_to: Position = match direction {
-            "UP" => Position::new(pos.get_row()+max_travel, pos.get_col()),
-            "DOWN" => Position::new(pos.get_row()-max_travel, pos.get_col()),
-            "LEFT" => Position::new(pos.get_row(), pos.get_col()-max_travel),
-            "RIGHT" => Position::new(pos.get_row(), pos.get_col()+max_travel),
-            "NE" => Position::new(pos.get_row()+max_travel, pos.get_col()+max_travel),
-            "NW" => Position::new(pos.get_row()+max_travel, pos.get_col()-max_travel),
-            "SE" => Position::new(pos.get_row()-max_travel, pos.get_col()+max_travel),
-            "SW" => Position::new(pos.get_row()-max_travel, pos.get_col()-max_travel),
-            _ => pos,
-        };
-        max_travel_to
+    }
 
+    #[test]
+    fn notation_names_radial_cells() {
+        let at = |notation: &str| Position::from_notation(Topology::RADIAL, notation);
+        assert_eq!(at("A1"), Some(Position::on(Topology::RADIAL, 0, 0)));
+        assert_eq!(at("P1"), Some(Position::on(Topology::RADIAL, 0, 15)));
+        assert_eq!(at("A4"), Some(Position::on(Topology::RADIAL, 3, 0)));
+        for bad in ["Q1", "A5", "A0", "", "1A", "AA"] {
+            assert_eq!(at(bad), None, "{}", bad);
+        }
+        assert_eq!(Position::from_notation(Topology::STANDARD, "I1"), None);
     }
-    
-}
\ No newline at end of file
+
+    #[test]
+    fn wrapping_boards() {
+        let (cylinder, torus) = (Topology::CYLINDER, Topology::TORUS);
+        assert_eq!(Position::on(cylinder, 0, -1), Position::on(cylinder, 0, 7));
+        assert_eq!(Position::on(cylinder, 0, 8), Position::on(cylinder, 0, 0));
+        assert!(Position::on(cylinder, -1, 0).is_off_board());
+        assert_eq!(Position::on(torus, -1, 0), Position::on(torus, 7, 0));
+        assert_eq!(Position::on(torus, 8, 9), Position::on(torus, 0, 1));
+
+        let a1 = |topology| Position::on(topology, 0, 0);
+        let h1 = |topology| Position::on(topology, 0, 7);
+        let h8 = |topology| Position::on(topology, 7, 7);
+        assert!(!a1(Topology::STANDARD).is_adjacent_to(h1(Topology::STANDARD)));
+        assert!(a1(cylinder).is_adjacent_to(h1(cylinder)));
+        assert!(!a1(cylinder).is_adjacent_to(h8(cylinder)));
+        assert!(a1(torus).is_adjacent_to(h8(torus)));
+        assert!(a1(cylinder).is_knight_move(Position::on(cylinder, 1, 6)));
+        assert_eq!(a1(torus).neighbours().len(), 8);
+        assert_eq!(a1(cylinder).neighbours().len(), 5);
+    }
+}
@@ -0,0 +1,1132 @@
+//src/variant.rs
+
+use std::fmt;
+use std::rc::Rc;
+
+use crate::{board::{Board, CastlingRights}, fen, piece::{Colour, Piece, PieceType}, position::{Position, Topology}};
+
+/// How a finished game ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Win { winner: Colour, reason: String },
+    Draw { reason: String },
+}
+
+impl Outcome {
+    /// The PGN result: `"1-0"`, `"0-1"` or `"1/2-1/2"`.
+    pub fn result(&self) -> &'static str {
+        match self {
+            Outcome::Win { winner: Colour::White, .. } => "1-0",
+            Outcome::Win { winner: Colour::Black, .. } => "0-1",
+            Outcome::Draw { .. } => "1/2-1/2",
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Win { winner, reason } => write!(f, "{:?} wins by {}.", winner, reason),
+            Outcome::Draw { reason } => write!(f, "Draw by {}.", reason),
+        }
+    }
+}
+
+/// A set of rules to play by. Every `Board` carries the variant it is being
+/// played under, and move generation, `Board::perft` and the game loop go through
+/// these hooks, so a new rule set only has to override the ones it changes.
+pub trait Variant {
+    /// Lower case, as given to `--variant`.
+    fn name(&self) -> &str;
+
+    /// The position a game starts from. `Board::start` attaches the variant to it.
+    fn starting_position(&self) -> Board;
+
+    /// Can `piece` make the (pseudo-legal) move to `to`? By default any move that
+    /// does not leave the mover's own king in check.
+    fn is_legal(&self, board: &Board, piece: Piece, to: Position) -> bool {
+        !self.is_in_check(&board.after_move(piece, to), piece.get_colour())
+    }
+
+    /// Is `colour`'s king in check? By default if any enemy piece attacks it.
+    fn is_in_check(&self, board: &Board, colour: Colour) -> bool {
+        board.is_in_check(colour)
+    }
+
+    /// Every legal move for the side to move, including drops from their pocket.
+    fn legal_moves(&self, board: &Board) -> Vec<(Piece, Position)> {
+        pseudo_legal_moves(board)
+            .into_iter()
+            .filter(|(piece, to)| self.is_legal(board, *piece, *to))
+            .collect()
+    }
+
+    /// Called by `Board` once `piece` has been moved to `to` (and promoted, if it
+    /// was a pawn reaching the last rank), before the turn passes. `piece` is as it
+    /// was before the move, or `Piece::Captured` for a drop. `captured` is the
+    /// piece taken, if any, including a pawn taken en passant.
+    fn after_move(&self, _board: &mut Board, _piece: Piece, _to: Position, _captured: Option<Piece>) {}
+
+    /// Are captured pieces kept in hand? If so FEN shows the pockets even when empty.
+    fn has_pockets(&self) -> bool {
+        false
+    }
+
+    /// Can a pawn of `colour` on `pos` make a double step? By default only from
+    /// where pawns start, see `Position::is_starting_pawn`.
+    fn can_double_step(&self, pos: Position, colour: Colour) -> bool {
+        pos.is_starting_pawn(colour)
+    }
+
+    /// Can kings castle at all?
+    fn allows_castling(&self) -> bool {
+        true
+    }
+
+    /// Does each player only see the squares their pieces can reach? See
+    /// `Board::visible_to`.
+    fn has_fog(&self) -> bool {
+        false
+    }
+
+    /// Does giving check count towards winning? If so FEN shows the checks given
+    /// even when there are none.
+    fn counts_checks(&self) -> bool {
+        false
+    }
+
+    /// Is every move followed by moving the duck? See `Board::play_duck_move`.
+    fn has_duck(&self) -> bool {
+        false
+    }
+
+    /// What a pawn may promote to, the first being the default.
+    fn promotions(&self, _board: &Board) -> Vec<PieceType> {
+        vec![PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight]
+    }
+
+    /// What stops play starting from `board`, for `Board::validate`. By default
+    /// each side needs exactly one king, no pawn may stand on the first or last
+    /// rank and the side that has just moved cannot be in check.
+    fn setup_problems(&self, board: &Board) -> Vec<String> {
+        let mut problems: Vec<String> = [Colour::White, Colour::Black].into_iter()
+            .filter_map(|colour| wrong_kings(board, colour, 1))
+            .collect();
+        problems.extend(pawns_on_back_ranks(board, |_| false));
+        if self.is_in_check(board, !board.turn) {
+            problems.push(format!("{:?} is in check with {:?} to move", !board.turn, board.turn));
+        }
+        problems
+    }
+
+    /// `Some` once the game is over: by default on checkmate, stalemate, the
+    /// fifty-move rule or when neither side has mating material.
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        if let Some(outcome) = checkmate_or_stalemate(board) {
+            return Some(outcome);
+        }
+        if board.halfmove_clock >= 100 {
+            return Some(Outcome::Draw { reason: "the fifty-move rule".to_string() });
+        }
+        if board.has_insufficient_material(Colour::White) && board.has_insufficient_material(Colour::Black) {
+            return Some(Outcome::Draw { reason: "insufficient material".to_string() });
+        }
+        None
+    }
+}
+
+/// Every move and drop the side to move could make, legal or not.
+pub fn pseudo_legal_moves(board: &Board) -> Vec<(Piece, Position)> {
+    board.state().into_iter()
+        .filter(|piece| piece.get_colour() == board.turn)
+        .flat_map(|piece| piece.pseudo_legal_moves(board).into_iter().map(move |to| (piece, to)))
+        .chain(board.drops())
+        .collect()
+}
+
+/// `Some` if the side to move has no legal moves.
+pub fn checkmate_or_stalemate(board: &Board) -> Option<Outcome> {
+    if !board.legal_moves().is_empty() {
+        return None;
+    }
+    Some(if board.variant.is_in_check(board, board.turn) {
+        Outcome::Win { winner: !board.turn, reason: "checkmate".to_string() }
+    } else {
+        Outcome::Draw { reason: "stalemate".to_string() }
+    })
+}
+
+/// A problem if `colour` does not have exactly `kings` kings.
+fn wrong_kings(board: &Board, colour: Colour, kings: usize) -> Option<String> {
+    let count = board.state().iter()
+        .filter(|piece| piece.get_piece_type() == PieceType::King && piece.get_colour() == colour)
+        .count();
+    (count != kings).then(|| format!("{:?} has {} kings", colour, count))
+}
+
+/// A problem for each pawn on the first or last rank, other than those `allowed`
+/// to stand there.
+fn pawns_on_back_ranks(board: &Board, allowed: impl Fn(&Piece) -> bool) -> Vec<String> {
+    board.state().iter()
+        .filter(|piece| piece.get_piece_type() == PieceType::Pawn && !allowed(piece))
+        .map(|pawn| pawn.get_pos())
+        .filter(|pos| pos.is_back_rank(Colour::White) || pos.is_back_rank(Colour::Black))
+        .map(|pos| format!("pawn on {}", pos.to_notation()))
+        .collect()
+}
+
+/// Standard chess.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Standard;
+
+impl Variant for Standard {
+    fn name(&self) -> &str {
+        "standard"
+    }
+
+    fn starting_position(&self) -> Board {
+        Board::new(Board::STANDARD_SETUP)
+    }
+}
+
+/// Crazyhouse: a captured piece goes into the capturer's pocket, and dropping a
+/// piece from the pocket onto an empty square is a move. Promoted pieces go back
+/// to being pawns when they are captured.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Crazyhouse;
+
+impl Variant for Crazyhouse {
+    fn name(&self) -> &str {
+        "crazyhouse"
+    }
+
+    fn starting_position(&self) -> Board {
+        Board::new(Board::STANDARD_SETUP)
+    }
+
+    fn after_move(&self, board: &mut Board, piece: Piece, to: Position, captured: Option<Piece>) {
+        if let Some(captured) = captured {
+            board.pockets.add(piece.get_colour(), in_hand(board, captured));
+        }
+        track_promotions(board, piece, to, captured);
+    }
+
+    fn has_pockets(&self) -> bool {
+        true
+    }
+
+    /// There is always material to drop, so no draw by insufficient material.
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        checkmate_or_stalemate(board).or_else(|| {
+            (board.halfmove_clock >= 100).then(|| Outcome::Draw { reason: "the fifty-move rule".to_string() })
+        })
+    }
+}
+
+/// What `captured` becomes once taken into a pocket: promoted pieces go back to
+/// being pawns. Must be asked before `track_promotions` forgets the piece.
+pub fn in_hand(board: &Board, captured: Piece) -> PieceType {
+    if board.promoted.contains(&captured.get_pos()) {
+        PieceType::Pawn
+    } else {
+        captured.get_piece_type()
+    }
+}
+
+/// Keep `board.promoted` up to date once `piece` has moved to `to`: a promoted
+/// piece that is taken is forgotten, one that moves is followed, and a pawn that
+/// has just promoted is added.
+fn track_promotions(board: &mut Board, piece: Piece, to: Position, captured: Option<Piece>) {
+    if let Some(captured) = captured {
+        board.promoted.retain(|pos| *pos != captured.get_pos());
+    }
+    if let Piece::Captured(_, _) = piece {
+        return;
+    }
+    if let Some(pos) = board.promoted.iter_mut().find(|pos| **pos == piece.get_pos()) {
+        *pos = to;
+    } else if piece.get_piece_type() == PieceType::Pawn && board.get_piece(to).is_some_and(|p| p.get_piece_type() != PieceType::Pawn) {
+        board.promoted.push(to);
+    }
+}
+
+/// The rules on each board of a Bughouse game (see `bughouse::BughouseGame`):
+/// Crazyhouse, except that captured pieces go to the capturer's partner on the
+/// other board, which the game hands over, rather than into their own pocket.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bughouse;
+
+impl Variant for Bughouse {
+    fn name(&self) -> &str {
+        "bughouse"
+    }
+
+    fn starting_position(&self) -> Board {
+        Board::new(Board::STANDARD_SETUP)
+    }
+
+    fn after_move(&self, board: &mut Board, piece: Piece, to: Position, captured: Option<Piece>) {
+        track_promotions(board, piece, to, captured);
+    }
+
+    fn has_pockets(&self) -> bool {
+        true
+    }
+
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        Crazyhouse.outcome(board)
+    }
+}
+
+/// Atomic chess: a capture is an explosion that removes the capturing piece along
+/// with every piece other than a pawn next to the square. Kings cannot capture, a
+/// move may not blow up the mover's own king, and blowing up the enemy king wins
+/// even if it leaves the mover in check. Kings standing next to each other cannot
+/// be in check, as neither side can capture without losing its own king.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Atomic;
+
+impl Variant for Atomic {
+    fn name(&self) -> &str {
+        "atomic"
+    }
+
+    fn starting_position(&self) -> Board {
+        Board::new(Board::STANDARD_SETUP)
+    }
+
+    fn is_legal(&self, board: &Board, piece: Piece, to: Position) -> bool {
+        let colour = piece.get_colour();
+        if piece.get_piece_type() == PieceType::King && board.has_enemy_piece(to, colour) {
+            return false;
+        }
+        let after = board.after_move(piece, to);
+        match (after.find_king(colour), after.find_king(!colour)) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(_), Some(_)) => !self.is_in_check(&after, colour),
+        }
+    }
+
+    fn is_in_check(&self, board: &Board, colour: Colour) -> bool {
+        match (board.find_king(colour), board.find_king(!colour)) {
+            (Some(king), Some(enemy_king)) if king.is_adjacent_to(enemy_king) => false,
+            _ => board.is_in_check(colour),
+        }
+    }
+
+    fn after_move(&self, board: &mut Board, _piece: Piece, to: Position, captured: Option<Piece>) {
+        if captured.is_none() {
+            return;
+        }
+        let exploded = board.state().into_iter()
+            .filter(|piece| piece.get_piece_type() != PieceType::Pawn && piece.get_pos().is_adjacent_to(to))
+            .map(|piece| piece.get_pos())
+            .chain([to]);
+        for pos in exploded.collect::<Vec<Position>>() {
+            board.remove(pos);
+            for colour in [Colour::White, Colour::Black] {
+                if pos.is_kingside_rook(&board.castling, colour) {
+                    board.castling.set(colour, true, None);
+                }
+                if pos.is_queenside_rook(&board.castling, colour) {
+                    board.castling.set(colour, false, None);
+                }
+            }
+        }
+    }
+
+    /// The game also ends as soon as a king is blown up, and with kings alone
+    /// neither side can win.
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        for colour in [Colour::White, Colour::Black] {
+            if board.find_king(colour).is_none() {
+                return Some(Outcome::Win { winner: !colour, reason: "blowing up the king".to_string() });
+            }
+        }
+        if let Some(outcome) = checkmate_or_stalemate(board) {
+            return Some(outcome);
+        }
+        if board.halfmove_clock >= 100 {
+            return Some(Outcome::Draw { reason: "the fifty-move rule".to_string() });
+        }
+        if board.state().iter().all(|piece| piece.get_piece_type() == PieceType::King) {
+            return Some(Outcome::Draw { reason: "insufficient material".to_string() });
+        }
+        None
+    }
+}
+
+/// Three-check: giving check for the third time wins, on top of the usual ways
+/// to win. Only a bare king cannot give check, so any other material is enough.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreeCheck;
+
+impl ThreeCheck {
+    fn winner(board: &Board) -> Option<Colour> {
+        [Colour::White, Colour::Black].into_iter().find(|colour| board.checks.get(*colour) >= 3)
+    }
+}
+
+impl Variant for ThreeCheck {
+    fn name(&self) -> &str {
+        "three-check"
+    }
+
+    fn starting_position(&self) -> Board {
+        Board::new(Board::STANDARD_SETUP)
+    }
+
+    /// None once the third check has been given.
+    fn legal_moves(&self, board: &Board) -> Vec<(Piece, Position)> {
+        if Self::winner(board).is_some() {
+            return Vec::new();
+        }
+        Standard.legal_moves(board)
+    }
+
+    fn after_move(&self, board: &mut Board, piece: Piece, _to: Position, _captured: Option<Piece>) {
+        let colour = piece.get_colour();
+        if board.is_in_check(!colour) {
+            board.checks.set(colour, board.checks.get(colour) + 1);
+        }
+    }
+
+    fn counts_checks(&self) -> bool {
+        true
+    }
+
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        if let Some(winner) = Self::winner(board) {
+            return Some(Outcome::Win { winner, reason: "giving three checks".to_string() });
+        }
+        if let Some(outcome) = checkmate_or_stalemate(board) {
+            return Some(outcome);
+        }
+        if board.halfmove_clock >= 100 {
+            return Some(Outcome::Draw { reason: "the fifty-move rule".to_string() });
+        }
+        if board.state().iter().all(|piece| piece.get_piece_type() == PieceType::King) {
+            return Some(Outcome::Draw { reason: "insufficient material".to_string() });
+        }
+        None
+    }
+}
+
+/// King of the Hill: bringing your king to one of the centre squares (d4, d5, e4
+/// and e5 on an 8x8 board) wins, on top of the usual ways to win. As a king can
+/// always head for the centre there is no draw by insufficient material.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KingOfTheHill;
+
+impl KingOfTheHill {
+    /// Is `pos` one of the middle two ranks and files, or the middle one where
+    /// there is an odd number of them?
+    fn is_hill(board: &Board, pos: Position) -> bool {
+        let middle = |n: i32, i: i32| (n - 1) / 2 <= i && i <= n / 2;
+        middle(board.topology.rows(), pos.get_row()) && middle(board.topology.cols(), pos.get_col())
+    }
+
+    fn winner(board: &Board) -> Option<Colour> {
+        [Colour::White, Colour::Black].into_iter()
+            .find(|colour| board.find_king(*colour).is_some_and(|king| Self::is_hill(board, king)))
+    }
+}
+
+impl Variant for KingOfTheHill {
+    fn name(&self) -> &str {
+        "king-of-the-hill"
+    }
+
+    fn starting_position(&self) -> Board {
+        Board::new(Board::STANDARD_SETUP)
+    }
+
+    /// None once a king has reached the hill.
+    fn legal_moves(&self, board: &Board) -> Vec<(Piece, Position)> {
+        if Self::winner(board).is_some() {
+            return Vec::new();
+        }
+        Standard.legal_moves(board)
+    }
+
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        if let Some(winner) = Self::winner(board) {
+            return Some(Outcome::Win { winner, reason: "reaching the hill".to_string() });
+        }
+        checkmate_or_stalemate(board).or_else(|| {
+            (board.halfmove_clock >= 100).then(|| Outcome::Draw { reason: "the fifty-move rule".to_string() })
+        })
+    }
+}
+
+/// Antichess, or losing chess: whoever runs out of pieces or moves first wins.
+/// Capturing is compulsory when possible, there is no check, so the king is an
+/// ordinary piece that can be taken and that pawns can promote to, and there is
+/// no castling.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Antichess;
+
+impl Variant for Antichess {
+    fn name(&self) -> &str {
+        "antichess"
+    }
+
+    fn starting_position(&self) -> Board {
+        let mut board = Board::new(Board::STANDARD_SETUP);
+        board.castling = CastlingRights::NONE;
+        board
+    }
+
+    /// Only captures if there are any, and otherwise every move.
+    fn legal_moves(&self, board: &Board) -> Vec<(Piece, Position)> {
+        let moves = pseudo_legal_moves(board);
+        if moves.iter().any(|(piece, to)| board.is_capture(*piece, *to)) {
+            moves.into_iter().filter(|(piece, to)| board.is_capture(*piece, *to)).collect()
+        } else {
+            moves
+        }
+    }
+
+    fn is_in_check(&self, _board: &Board, _colour: Colour) -> bool {
+        false
+    }
+
+    fn allows_castling(&self) -> bool {
+        false
+    }
+
+    fn promotions(&self, _board: &Board) -> Vec<PieceType> {
+        vec![PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight, PieceType::King]
+    }
+
+    /// Any number of kings will do, as they are ordinary pieces.
+    fn setup_problems(&self, board: &Board) -> Vec<String> {
+        pawns_on_back_ranks(board, |_| false)
+    }
+
+    /// The side to move wins if they have nothing left to move.
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        if board.legal_moves().is_empty() {
+            let reason = if board.state().iter().any(|piece| piece.get_colour() == board.turn) {
+                "having no moves"
+            } else {
+                "losing every piece"
+            };
+            return Some(Outcome::Win { winner: board.turn, reason: reason.to_string() });
+        }
+        (board.halfmove_clock >= 100).then(|| Outcome::Draw { reason: "the fifty-move rule".to_string() })
+    }
+}
+
+/// Horde: white has 36 pawns and no king against black's usual army. White wins
+/// by checkmate and black by taking every white piece. White's pawns on the first
+/// rank may double step as well.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Horde;
+
+impl Horde {
+    pub const STARTING_FEN: &'static str = "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1";
+}
+
+impl Variant for Horde {
+    fn name(&self) -> &str {
+        "horde"
+    }
+
+    fn starting_position(&self) -> Board {
+        fen::from_fen(Topology::STANDARD, Self::STARTING_FEN).unwrap()
+    }
+
+    fn can_double_step(&self, pos: Position, colour: Colour) -> bool {
+        pos.is_starting_pawn(colour) || pos.is_back_rank(colour)
+    }
+
+    /// White has no king, and white pawns may stand on the first rank.
+    fn setup_problems(&self, board: &Board) -> Vec<String> {
+        let mut problems: Vec<String> = [(Colour::White, 0), (Colour::Black, 1)].into_iter()
+            .filter_map(|(colour, kings)| wrong_kings(board, colour, kings))
+            .collect();
+        problems.extend(pawns_on_back_ranks(board, |pawn| {
+            pawn.get_colour() == Colour::White && pawn.get_pos().is_back_rank(Colour::White)
+        }));
+        if self.is_in_check(board, !board.turn) {
+            problems.push(format!("{:?} is in check with {:?} to move", !board.turn, board.turn));
+        }
+        problems
+    }
+
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        if board.state().iter().all(|piece| piece.get_colour() == Colour::Black) {
+            return Some(Outcome::Win { winner: Colour::Black, reason: "taking the whole horde".to_string() });
+        }
+        checkmate_or_stalemate(board).or_else(|| {
+            (board.halfmove_clock >= 100).then(|| Outcome::Draw { reason: "the fifty-move rule".to_string() })
+        })
+    }
+}
+
+/// Racing Kings: the first king to reach the last rank wins. Neither king may
+/// ever be in check, so giving check is illegal too. As white moves first, black
+/// still gets a move after white's king arrives, and the game is drawn if black's
+/// king then arrives as well.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RacingKings;
+
+impl RacingKings {
+    pub const STARTING_FEN: &'static str = "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1";
+
+    fn has_arrived(board: &Board, colour: Colour) -> bool {
+        board.find_king(colour).is_some_and(|king| king.is_back_rank(Colour::Black))
+    }
+
+    /// Is the race over? Not yet if white's king has arrived but black's king
+    /// can still catch up on this move.
+    fn is_over(board: &Board) -> bool {
+        let (white, black) = (Self::has_arrived(board, Colour::White), Self::has_arrived(board, Colour::Black));
+        if !white && !black {
+            return false;
+        }
+        if black || board.turn == Colour::White {
+            return true;
+        }
+        let Some(king) = board.find_king(Colour::Black) else {
+            return true;
+        };
+        !king.neighbours().into_iter().any(|to| {
+            to.is_back_rank(Colour::Black)
+                && !board.has_friendly_piece(to, Colour::Black)
+                && !board.is_attacked(to, Colour::White)
+        })
+    }
+}
+
+impl Variant for RacingKings {
+    fn name(&self) -> &str {
+        "racing-kings"
+    }
+
+    fn starting_position(&self) -> Board {
+        fen::from_fen(Topology::STANDARD, Self::STARTING_FEN).unwrap()
+    }
+
+    /// Moves that leave either king in check are illegal.
+    fn is_legal(&self, board: &Board, piece: Piece, to: Position) -> bool {
+        let after = board.after_move(piece, to);
+        !after.is_in_check(Colour::White) && !after.is_in_check(Colour::Black)
+    }
+
+    /// None once the race is over.
+    fn legal_moves(&self, board: &Board) -> Vec<(Piece, Position)> {
+        if Self::is_over(board) {
+            return Vec::new();
+        }
+        pseudo_legal_moves(board)
+            .into_iter()
+            .filter(|(piece, to)| self.is_legal(board, *piece, *to))
+            .collect()
+    }
+
+    fn allows_castling(&self) -> bool {
+        false
+    }
+
+    /// There are no pawns, and neither king may be in check whoever is to move.
+    fn setup_problems(&self, board: &Board) -> Vec<String> {
+        let mut problems: Vec<String> = [Colour::White, Colour::Black].into_iter()
+            .filter_map(|colour| wrong_kings(board, colour, 1))
+            .collect();
+        problems.extend(board.state().iter()
+            .filter(|piece| piece.get_piece_type() == PieceType::Pawn)
+            .map(|pawn| format!("pawn on {}", pawn.get_pos().to_notation())));
+        for colour in [Colour::White, Colour::Black] {
+            if self.is_in_check(board, colour) {
+                problems.push(format!("{:?} is in check", colour));
+            }
+        }
+        problems
+    }
+
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        if Self::is_over(board) {
+            return Some(match (Self::has_arrived(board, Colour::White), Self::has_arrived(board, Colour::Black)) {
+                (true, true) => Outcome::Draw { reason: "both kings reaching the last rank".to_string() },
+                (true, false) => Outcome::Win { winner: Colour::White, reason: "reaching the last rank".to_string() },
+                _ => Outcome::Win { winner: Colour::Black, reason: "reaching the last rank".to_string() },
+            });
+        }
+        checkmate_or_stalemate(board).or_else(|| {
+            (board.halfmove_clock >= 100).then(|| Outcome::Draw { reason: "the fifty-move rule".to_string() })
+        })
+    }
+}
+
+/// Fog of war, or dark chess: each player sees only their own pieces and the
+/// squares those can move to. There is no check, so any move is allowed, even
+/// castling out of or through an attack, and the game is won by taking the king.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FogOfWar;
+
+impl Variant for FogOfWar {
+    fn name(&self) -> &str {
+        "fog-of-war"
+    }
+
+    fn starting_position(&self) -> Board {
+        Board::new(Board::STANDARD_SETUP)
+    }
+
+    fn is_legal(&self, _board: &Board, _piece: Piece, _to: Position) -> bool {
+        true
+    }
+
+    fn is_in_check(&self, _board: &Board, _colour: Colour) -> bool {
+        false
+    }
+
+    fn has_fog(&self) -> bool {
+        true
+    }
+
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        for colour in [Colour::White, Colour::Black] {
+            if board.find_king(colour).is_none() {
+                return Some(Outcome::Win { winner: !colour, reason: "capturing the king".to_string() });
+            }
+        }
+        checkmate_or_stalemate(board).or_else(|| {
+            (board.halfmove_clock >= 100).then(|| Outcome::Draw { reason: "the fifty-move rule".to_string() })
+        })
+    }
+}
+
+/// Duck chess: after every move the mover must put the duck, which blocks both
+/// sides and cannot be captured, on a different empty square. There is no check,
+/// the game is won by taking the king, and a player left without a move wins.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DuckChess;
+
+impl DuckChess {
+    /// The side whose king has been taken, if either has.
+    fn king_taken(board: &Board) -> Option<Colour> {
+        [Colour::White, Colour::Black].into_iter().find(|colour| board.find_king(*colour).is_none())
+    }
+}
+
+impl Variant for DuckChess {
+    fn name(&self) -> &str {
+        "duck-chess"
+    }
+
+    fn starting_position(&self) -> Board {
+        Board::new(Board::STANDARD_SETUP)
+    }
+
+    fn is_legal(&self, _board: &Board, _piece: Piece, _to: Position) -> bool {
+        true
+    }
+
+    fn is_in_check(&self, _board: &Board, _colour: Colour) -> bool {
+        false
+    }
+
+    fn legal_moves(&self, board: &Board) -> Vec<(Piece, Position)> {
+        if Self::king_taken(board).is_some() {
+            return Vec::new();
+        }
+        pseudo_legal_moves(board)
+    }
+
+    fn has_duck(&self) -> bool {
+        true
+    }
+
+    fn outcome(&self, board: &Board) -> Option<Outcome> {
+        if let Some(colour) = Self::king_taken(board) {
+            return Some(Outcome::Win { winner: !colour, reason: "capturing the king".to_string() });
+        }
+        if board.legal_moves().is_empty() {
+            return Some(Outcome::Win { winner: board.turn, reason: "stalemate".to_string() });
+        }
+        (board.halfmove_clock >= 100).then(|| Outcome::Draw { reason: "the fifty-move rule".to_string() })
+    }
+}
+
+/// The variant called `name`, for `--variant`.
+pub fn from_name(name: &str) -> Option<Rc<dyn Variant>> {
+    match name {
+        "standard" => Some(Rc::new(Standard)),
+        "crazyhouse" => Some(Rc::new(Crazyhouse)),
+        "atomic" => Some(Rc::new(Atomic)),
+        "three-check" => Some(Rc::new(ThreeCheck)),
+        "king-of-the-hill" => Some(Rc::new(KingOfTheHill)),
+        "antichess" => Some(Rc::new(Antichess)),
+        "horde" => Some(Rc::new(Horde)),
+        "racing-kings" => Some(Rc::new(RacingKings)),
+        "fog-of-war" => Some(Rc::new(FogOfWar)),
+        "duck-chess" => Some(Rc::new(DuckChess)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{board::DuckMove, pgn, position::{A1, A2, A7, A8, D1, D2, D4, E1, E2, E8, F1, H2}};
+
+    fn from_fen(variant: impl Variant + 'static, fen: &str) -> Board {
+        let mut board = fen::from_fen(Topology::STANDARD, fen).unwrap();
+        board.variant = Rc::new(variant);
+        board
+    }
+
+    fn perft(board: &Board, depth: u32) -> Vec<u64> {
+        (1..=depth).map(|depth| board.perft(depth)).collect()
+    }
+
+    /// Play `moves`, given in SAN, promoting to a queen.
+    fn play(board: &Board, moves: &[&str]) -> Board {
+        let mut board = board.clone();
+        for san in moves {
+            let (piece, to, _) = pgn::parse_san(&board, san).unwrap_or_else(|| panic!("{} is not legal in {}", san, fen::to_fen(&board)));
+            board = board.after_move(piece, to);
+        }
+        board
+    }
+
+    #[test]
+    fn crazyhouse_perft() {
+        let board = Board::start(Rc::new(Crazyhouse));
+        assert_eq!(perft(&board, 3), [20, 400, 8902]);
+        let board = from_fen(Crazyhouse, "2k5/8/8/8/8/8/8/4K3[QRBNPqrbnp] w - - 0 1");
+        assert_eq!(perft(&board, 2), [301, 75353]);
+    }
+
+    /// Slow in a debug build, run with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn crazyhouse_perft_5() {
+        assert_eq!(Board::start(Rc::new(Crazyhouse)).perft(5), 4888832);
+    }
+
+    #[test]
+    fn captures_go_to_the_pocket() {
+        let board = play(&Board::start(Rc::new(Crazyhouse)), &["e4", "d5", "exd5", "Qxd5"]);
+        assert_eq!(board.pockets.get(Colour::White), [PieceType::Pawn]);
+        assert_eq!(board.pockets.get(Colour::Black), [PieceType::Pawn]);
+
+        let board = play(&board, &["P@e6", "Qxe6+"]);
+        assert!(board.pockets.get(Colour::White).is_empty());
+        assert_eq!(board.pockets.get(Colour::Black), [PieceType::Pawn, PieceType::Pawn]);
+    }
+
+    #[test]
+    fn pawns_are_not_dropped_on_the_back_ranks() {
+        let board = from_fen(Crazyhouse, "4k3/8/8/8/8/8/8/4K3[PN] w - - 0 1");
+        let drops = board.drops();
+        let pawn_ranks: Vec<i32> = drops.iter()
+            .filter(|(piece, _)| piece.get_piece_type() == PieceType::Pawn)
+            .map(|(_, to)| to.get_row())
+            .collect();
+        assert_eq!(pawn_ranks.len(), 6 * 8);
+        assert!(pawn_ranks.iter().all(|row| (1..=6).contains(row)));
+        assert_eq!(drops.len() - pawn_ranks.len(), 64 - 2);
+        assert!(pgn::parse_san(&board, "P@d8").is_none());
+        assert!(pgn::parse_san(&board, "N@d8").is_some());
+    }
+
+    #[test]
+    fn promoted_pieces_are_pawns_in_hand() {
+        let board = from_fen(Crazyhouse, "r3k3/8/8/8/8/8/8/Q~3K3[] b - - 0 1");
+        assert_eq!(board.promoted, [A1]);
+        let board = play(&board, &["Rxa1+"]);
+        assert_eq!(board.pockets.get(Colour::Black), [PieceType::Pawn]);
+        assert!(board.promoted.is_empty());
+        assert_eq!(fen::to_fen(&board), "4k3/8/8/8/8/8/8/r3K3[p] w - - 0 2");
+
+        // A pawn promoting is marked, and is followed when it moves on
+        let board = play(&from_fen(Crazyhouse, "4k3/P7/8/8/8/8/8/4K3[] w - - 0 1"), &["a8=Q+", "Kd7", "Qb7+"]);
+        assert_eq!(fen::to_fen(&board), "8/1Q~1k4/8/8/8/8/8/4K3[] b - - 2 2");
+    }
+
+    #[test]
+    fn pockets_round_trip() {
+        for fen in ["4k3/8/8/8/8/8/8/4K3[Qn] w - - 0 1", "4k3/8/8/8/8/8/8/4K3[] w - - 0 1"] {
+            assert_eq!(fen::to_fen(&from_fen(Crazyhouse, fen)), fen);
+        }
+        let board = from_fen(Crazyhouse, "4k3/8/8/8/8/8/8/4K3/Qn w - - 0 1");
+        assert_eq!(board.pockets.get(Colour::White), [PieceType::Queen]);
+        assert_eq!(board.pockets.get(Colour::Black), [PieceType::Knight]);
+    }
+
+    #[test]
+    fn atomic_perft() {
+        assert_eq!(perft(&Board::start(Rc::new(Atomic)), 3), [20, 400, 8902]);
+        let board = from_fen(Atomic, "rn2kb1r/1pp1p2p/p2q1pp1/3P4/2P3b1/4PN2/PP3PPP/R2QKB1R b KQkq - 0 1");
+        assert_eq!(perft(&board, 3), [40, 1238, 45237]);
+    }
+
+    #[test]
+    fn captures_explode() {
+        // Every piece next to d5 goes, bar the pawns, along with the knight
+        let board = play(&from_fen(Atomic, "7k/8/2rpP3/3q4/4BN2/8/8/K7 w - - 0 1"), &["Nxd5"]);
+        assert_eq!(fen::to_fen(&board), "7k/8/3pP3/8/8/8/8/K7 b - - 0 1");
+
+        // Blowing up a rook gives up castling with it
+        let board = play(&from_fen(Atomic, "r3k3/1B6/8/8/8/8/8/4K3 w q - 0 1"), &["Bxa8"]);
+        assert!(board.castling.queenside(Colour::Black).is_none());
+    }
+
+    #[test]
+    fn kings_cannot_capture() {
+        let board = from_fen(Atomic, "4k3/8/8/8/8/8/3q4/4K3 w - - 0 1");
+        let king = board.get_piece(E1).unwrap();
+        assert_eq!(king.legal_moves(&board), [F1]);
+    }
+
+    #[test]
+    fn own_king_cannot_be_blown_up() {
+        let board = from_fen(Atomic, "4k3/8/8/8/8/8/3n4/3QK3 w - - 0 1");
+        assert!(pgn::parse_san(&board, "Qxd2").is_none());
+    }
+
+    #[test]
+    fn blowing_up_the_king_wins() {
+        // Even out of check, as the game ends at once
+        let board = from_fen(Atomic, "3rk3/3p4/8/8/8/8/8/3RK2q w - - 0 1");
+        assert!(Atomic.is_in_check(&board, Colour::White));
+        let board = play(&board, &["Rxd7"]);
+        assert_eq!(board.find_king(Colour::Black), None);
+        assert_eq!(board.variant.outcome(&board), Some(Outcome::Win { winner: Colour::White, reason: "blowing up the king".to_string() }));
+    }
+
+    #[test]
+    fn connected_kings_are_never_in_check() {
+        let board = from_fen(Atomic, "4r3/8/8/8/8/8/3k4/4K3 w - - 0 1");
+        assert!(board.is_in_check(Colour::White));
+        assert!(!Atomic.is_in_check(&board, Colour::White));
+        assert_eq!(board.variant.outcome(&board), None);
+        // So the king can stay on the rook's file as long as it stays connected
+        let king = board.get_piece(E1).unwrap();
+        assert!(king.legal_moves(&board).contains(&E2));
+        let board = from_fen(Atomic, "4r3/8/8/8/8/8/8/2k1K3 w - - 0 1");
+        let king = board.get_piece(E1).unwrap();
+        assert!(!king.legal_moves(&board).contains(&E2));
+    }
+
+    #[test]
+    fn checks_are_counted() {
+        let board = play(&Board::start(Rc::new(ThreeCheck)), &["e4", "e5", "Bc4", "Nc6", "Bxf7+"]);
+        assert_eq!(board.checks.get(Colour::White), 1);
+        assert_eq!(board.checks.get(Colour::Black), 0);
+        assert!(fen::to_fen(&board).ends_with(" b KQkq - 0 3 +1+0"));
+        let board = play(&board, &["Kxf7"]);
+        assert_eq!(board.checks.get(Colour::White), 1);
+        assert_eq!(Board::start(Rc::new(ThreeCheck)).perft(3), 8902);
+    }
+
+    #[test]
+    fn check_counts_round_trip() {
+        let given = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2 +2+0";
+        assert_eq!(fen::to_fen(&from_fen(ThreeCheck, given)), given);
+
+        // The checks still to give, after the en passant square, are written as given
+        let remaining = from_fen(ThreeCheck, "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1+3 0 2");
+        assert_eq!(fen::to_fen(&remaining), given);
+        let board = from_fen(ThreeCheck, "4k3/8/8/8/8/8/8/4K3 w - - 3+3 0 1");
+        assert_eq!(fen::to_fen(&board), "4k3/8/8/8/8/8/8/4K3 w - - 0 1 +0+0");
+        assert!(fen::from_fen(Topology::STANDARD, "4k3/8/8/8/8/8/8/4K3 w - - 0 1 +4+0").is_err());
+    }
+
+    #[test]
+    fn third_check_wins() {
+        let board = from_fen(ThreeCheck, "4k3/8/8/8/8/8/8/R3K3 w - - 0 1 +2+0");
+        assert_eq!(board.variant.outcome(&board), None);
+        let board = play(&board, &["Ra8+"]);
+        assert_eq!(board.checks.get(Colour::White), 3);
+        assert!(board.legal_moves().is_empty());
+        assert_eq!(board.variant.outcome(&board), Some(Outcome::Win { winner: Colour::White, reason: "giving three checks".to_string() }));
+    }
+
+    #[test]
+    fn reaching_the_hill_wins() {
+        for (square, wins) in [("d4", true), ("e4", true), ("d5", true), ("e5", true), ("c4", false), ("e6", false), ("f5", false)] {
+            let mut board = from_fen(KingOfTheHill, "k7/8/8/8/8/8/8/7K w - - 0 1");
+            let king = board.find_king(Colour::White).unwrap();
+            board.remove(king);
+            board.place(PieceType::King, Colour::White, board.notation_to_position(square).unwrap());
+            let outcome = board.variant.outcome(&board);
+            assert_eq!(outcome.is_some(), wins, "{}", square);
+            assert_eq!(board.legal_moves().is_empty(), wins, "{}", square);
+        }
+
+        let board = play(&from_fen(KingOfTheHill, "k7/8/8/8/8/4K3/8/8 w - - 0 1"), &["Ke4"]);
+        assert_eq!(board.variant.outcome(&board), Some(Outcome::Win { winner: Colour::White, reason: "reaching the hill".to_string() }));
+        assert_eq!(Board::start(Rc::new(KingOfTheHill)).perft(3), 8902);
+    }
+
+    #[test]
+    fn antichess_captures_are_compulsory() {
+        let start = Board::start(Rc::new(Antichess));
+        assert_eq!(perft(&start, 3), [20, 400, 8067]);
+
+        let board = play(&start, &["e3", "b5"]);
+        let moves: Vec<String> = board.legal_moves().into_iter()
+            .map(|(piece, to)| pgn::to_san(&board, piece, to, PieceType::Queen))
+            .collect();
+        assert_eq!(moves, ["Bxb5"]);
+    }
+
+    #[test]
+    fn antichess_kings_are_ordinary_pieces() {
+        // Taking the king is just a capture, and here the only move
+        let board = from_fen(Antichess, "4k3/8/8/8/8/8/8/4R2K w - - 0 1");
+        assert_eq!(board.legal_moves().len(), 1);
+        let board = play(&board, &["Rxe8"]);
+        assert_eq!(board.find_king(Colour::Black), None);
+
+        let board = from_fen(Antichess, "8/P7/8/8/8/8/8/7k w - - 0 1");
+        assert_eq!(board.perft(1), 5);
+        let pawn = board.get_piece(A7).unwrap();
+        let board = board.move_piece_with_promotion(pawn, A8, Colour::White, PieceType::King);
+        assert_eq!(fen::to_fen(&board), "K7/8/8/8/8/8/8/7k b - - 0 1");
+    }
+
+    #[test]
+    fn antichess_is_won_by_running_out() {
+        let win = |reason: &str| Some(Outcome::Win { winner: Colour::Black, reason: reason.to_string() });
+        let board = play(&from_fen(Antichess, "4k3/8/8/8/8/8/8/4R2K w - - 0 1"), &["Rxe8"]);
+        assert_eq!(board.variant.outcome(&board), win("losing every piece"));
+
+        let board = from_fen(Antichess, "8/8/8/8/8/p7/P7/8 b - - 0 1");
+        assert_eq!(board.variant.outcome(&board), win("having no moves"));
+        let board = from_fen(Antichess, "8/8/8/8/8/p7/P7/8 w - - 0 1");
+        assert_eq!(board.variant.outcome(&board).map(|outcome| outcome.result()), Some("1-0"));
+    }
+
+    #[test]
+    fn racing_kings_never_check() {
+        let start = Board::start(Rc::new(RacingKings));
+        assert_eq!(perft(&start, 3), [21, 421, 11264]);
+        for (piece, to) in start.legal_moves() {
+            let after = start.after_move(piece, to);
+            assert!(!after.is_in_check(Colour::White) && !after.is_in_check(Colour::Black));
+        }
+
+        let board = from_fen(RacingKings, "8/8/8/8/8/8/k7/6RK w - - 0 1");
+        assert!(pgn::parse_san(&board, "Rg2").is_none());
+        assert!(pgn::parse_san(&board, "Rg3").is_some());
+    }
+
+    #[test]
+    fn black_can_still_draw_the_race() {
+        let board = play(&from_fen(RacingKings, "8/1k4K1/8/8/8/8/8/8 w - - 0 1"), &["Kg8"]);
+        assert_eq!(board.variant.outcome(&board), None);
+        let drawn = play(&board, &["Kb8"]);
+        assert_eq!(drawn.variant.outcome(&drawn), Some(Outcome::Draw { reason: "both kings reaching the last rank".to_string() }));
+        let lost = play(&board, &["Kb6"]);
+        assert_eq!(lost.variant.outcome(&lost).map(|outcome| outcome.result()), Some("1-0"));
+
+        // Too far behind to catch up
+        let board = play(&from_fen(RacingKings, "8/6K1/1k6/8/8/8/8/8 w - - 0 1"), &["Kg8"]);
+        assert_eq!(board.variant.outcome(&board), Some(Outcome::Win { winner: Colour::White, reason: "reaching the last rank".to_string() }));
+    }
+
+    #[test]
+    fn horde() {
+        let start = Board::start(Rc::new(Horde));
+        assert_eq!(perft(&start, 3), [8, 128, 1274]);
+        assert!(pgn::parse_san(&from_fen(Horde, "4k3/8/8/8/8/8/8/P7 w - - 0 1"), "a3").is_some());
+
+        // White has no king to mate, but loses by having nothing left
+        let board = from_fen(Horde, "4k3/8/8/8/8/8/1q6/P7 b - - 0 1");
+        assert_eq!(board.variant.outcome(&board), None);
+        let board = play(&board, &["Qxa1"]);
+        assert_eq!(board.variant.outcome(&board), Some(Outcome::Win { winner: Colour::Black, reason: "taking the whole horde".to_string() }));
+    }
+
+    /// The problems `Board::validate` finds in `fen` under `variant`.
+    fn problems(variant: impl Variant + 'static, fen: &str) -> Vec<String> {
+        let board = from_fen(variant, fen);
+        board.variant.setup_problems(&board)
+    }
+
+    #[test]
+    fn setups_follow_the_variant() {
+        assert!(problems(Standard, "4k3/8/8/8/8/8/8/4K3 w - - 0 1").is_empty());
+        assert_eq!(problems(Standard, "P7/8/8/8/8/8/8/4K1R1 w - - 0 1"), ["Black has 0 kings", "pawn on A8"]);
+        assert_eq!(problems(Standard, "4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"), ["Black is in check with White to move"]);
+
+        // Horde has no white king, and the horde may stand on the first rank
+        assert!(from_fen(Horde, Horde::STARTING_FEN).validate().is_ok());
+        assert_eq!(problems(Horde, "4k3/8/8/8/8/8/8/PPPPKPPP b - - 0 1"), ["White has 1 kings"]);
+        assert_eq!(problems(Horde, "P3k3/8/8/8/8/8/8/pPPPPPPP b - - 0 1"), ["pawn on A1", "pawn on A8"]);
+
+        // In Racing Kings neither king may be in check and there are no pawns
+        assert!(from_fen(RacingKings, RacingKings::STARTING_FEN).validate().is_ok());
+        assert!(problems(Standard, "8/8/8/8/8/8/k6r/7K w - - 0 1").is_empty());
+        assert_eq!(problems(RacingKings, "8/8/8/8/8/8/k6r/7K w - - 0 1"), ["White is in check"]);
+        assert_eq!(problems(RacingKings, "8/8/8/8/8/P7/k7/7K w - - 0 1"), ["pawn on A3"]);
+
+        // Kings side by side are never in check in Atomic, but otherwise check counts
+        assert!(!problems(Standard, "4R3/8/8/4k3/4K3/8/8/8 w - - 0 1").is_empty());
+        assert!(problems(Atomic, "4R3/8/8/4k3/4K3/8/8/8 w - - 0 1").is_empty());
+        assert_eq!(problems(Atomic, "4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"), ["Black is in check with White to move"]);
+
+        // Kings are ordinary pieces in Antichess
+        assert!(problems(Antichess, "8/8/8/8/8/8/8/R7 b - - 0 1").is_empty());
+        assert!(problems(Antichess, "4k3/8/8/8/8/8/8/4R1K1 w - - 0 1").is_empty());
+        assert_eq!(problems(Antichess, "8/8/8/8/8/8/8/p7 w - - 0 1"), ["pawn on A1"]);
+    }
+
+    #[test]
+    fn the_duck_must_move() {
+        let board = from_fen(DuckChess, "4k3/8/8/8/3*4/8/8/4K3 w - - 0 1");
+        let king = board.get_piece(E1).unwrap();
+        let duck_move = |duck| DuckMove { piece: king, to: E2, duck };
+
+        // Not left where it is, nor put on a piece, but the square just left is fine
+        for duck in [D4, E2, E8] {
+            assert_eq!(fen::to_fen(&board.clone().play_duck_move(duck_move(duck), PieceType::Queen)), fen::to_fen(&board));
+        }
+        let after = board.clone().play_duck_move(duck_move(E1), PieceType::Queen);
+        assert_eq!(fen::to_fen(&after), "4k3/8/8/8/8/8/4K3/4*3 b - - 1 1");
+
+        let squares = board.after_move(king, E2).duck_squares();
+        assert_eq!(squares.len(), 64 - 3);
+        assert!(squares.contains(&E1) && !squares.contains(&D4));
+        assert_eq!(Board::start(Rc::new(DuckChess)).perft(1), 20 * 32);
+    }
+
+    #[test]
+    fn the_duck_blocks_everyone() {
+        let board = from_fen(DuckChess, "4k3/8/8/8/8/8/3*4/R3K3 w - - 0 1");
+        let rook = board.get_piece(A1).unwrap();
+        let moves = rook.legal_moves(&board);
+        assert!(moves.contains(&D1) && !moves.contains(&D2));
+
+        let board = from_fen(DuckChess, "4k3/8/8/8/8/8/R2*3r/4K3 w - - 0 1");
+        let rook = board.get_piece(A2).unwrap();
+        let moves = rook.legal_moves(&board);
+        assert_eq!(moves.iter().filter(|to| to.get_row() == 1).count(), 2);
+        assert!(!board.legal_moves().iter().any(|(_, to)| *to == D2));
+        assert!(!board.is_attacked(H2, Colour::White));
+    }
+
+    #[test]
+    fn duck_fen_round_trips() {
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP*PPP/RNBQKBNR b KQkq - 0 1";
+        let board = from_fen(DuckChess, fen);
+        assert_eq!(board.duck(), Some(E2));
+        assert_eq!(board.state().len(), 32);
+        assert_eq!(fen::to_fen(&board), fen);
+    }
+
+    #[test]
+    fn taking_the_king_wins_duck_chess() {
+        let board = from_fen(DuckChess, "4k3/8/8/8/8/8/8/4RK1* w - - 0 1");
+        let rook = board.get_piece(E1).unwrap();
+        let board = board.play_duck_move(DuckMove { piece: rook, to: E8, duck: D4 }, PieceType::Queen);
+        assert_eq!(board.variant.outcome(&board), Some(Outcome::Win { winner: Colour::White, reason: "capturing the king".to_string() }));
+    }
+}